@@ -1,63 +1,218 @@
-use std::{
-    env::current_dir, error::Error, fs::metadata, path::PathBuf, process,
-};
+use std::{io, path::Path, process};
+
+use clap::CommandFactory;
+use clap_complete::{env::Shells, CompleteEnv};
 
 use skel::{
-    config::resolve_config,
-    parse_args::{parse_args, SkelArgs},
-    project_tree::make_project_tree,
+    commands::{
+        print_config_sources, print_install, print_pack, print_skeletons,
+        print_source_updates,
+    },
+    config::SkelExec,
+    error::SkelError,
+    generator::Generator,
+    output::ArchiveOutput,
+    parse_args::{
+        parse_args, ConfigCommand, SkelArgs, SkelCommand, SourcesCommand,
+    },
+    picker::{ask_project_name, ask_vars, pick_skeleton},
+    report::Verbosity,
+    style::{paint, set_color_choice, Stream, Style},
 };
 
-fn get_root(args: &SkelArgs) -> Result<PathBuf, Box<dyn Error>> {
-    if let Some(diff_root) = &args.different_root {
-        let diff_root_path = PathBuf::from(diff_root);
-
-        if diff_root_path.is_dir() {
-            Ok(diff_root_path)
-        } else {
-            Err(Box::from(format!(
-                "different root does not exists or is not a dir {}",
-                diff_root
-            )))
+// turn the cli args in to a generator, a skeleton file of `-` is read from
+// stdin
+fn make_generator(args: &SkelArgs) -> Result<Generator, SkelError> {
+    let mut generator = Generator::new()
+        .dry_run(args.dry_run)
+        .parents(args.parents)
+        .no_build(args.no_build)
+        .trust(args.trust)
+        .verbosity(Verbosity::from_flags(args.quiet, args.verbose))
+        .exec(SkelExec {
+            clear_env: args.clear_env.then_some(true),
+            env_allow: (!args.env_allow.is_empty())
+                .then(|| args.env_allow.clone()),
+            timeout: args.timeout,
+            umask: args.umask.clone(),
+            limits: args.limits.clone(),
+            ..Default::default()
+        });
+
+    if let Some(ref skeleton) = args.skeleton {
+        generator = generator.skeleton(skeleton);
+    }
+
+    match args.skeleton_file.as_deref() {
+        Some("-") => {
+            let text = io::read_to_string(io::stdin())
+                .map_err(|e| SkelError::io(Path::new("-"), e))?;
+
+            generator = generator.skeleton_text(text);
         }
-    } else {
-        Ok(current_dir().expect("could not get current dir"))
+        Some(skeleton_file) => {
+            generator = generator.skeleton_path(skeleton_file);
+        }
+        None => {}
+    }
+
+    if let Some(ref config_path) = args.alt_config_path {
+        generator = generator.config_path(config_path);
+    }
+
+    if let Some(ref name) = args.name {
+        generator = generator.name(name);
+    }
+
+    if let Some(ref target) = args.target {
+        generator = generator.target(target);
+    }
+
+    if let Some(ref root) = args.different_root {
+        generator = generator.root(root);
+    }
+
+    if let Some(ref log_file) = args.log_file {
+        generator = generator.log_file(log_file);
+    }
+
+    for (key, value) in args.vars.iter() {
+        generator = generator.var(key, value);
     }
+
+    Ok(generator)
+}
+
+// the script that registers skel with a shell, the script calls back in to
+// skel to complete so the skeleton names come from the same configs
+fn print_completions(shell: &str) -> Result<(), SkelError> {
+    let shells = Shells::builtins();
+
+    let completer = shells
+        .completer(shell)
+        .ok_or_else(|| format!("can not make completions for {}", shell))?;
+
+    completer
+        .write_registration(
+            "COMPLETE",
+            "skel",
+            "skel",
+            "skel",
+            &mut io::stdout(),
+        )
+        .map_err(|e| SkelError::io(Path::new("-"), e))?;
+
+    Ok(())
 }
 
 // the real `main()` so we can clean up before `process::exit()`
-fn run() -> Result<(), Box<dyn Error>> {
-    let args = parse_args()?;
+fn run() -> Result<(), SkelError> {
+    let mut args = parse_args()?;
+
+    set_color_choice(args.color);
+
+    let verbosity = Verbosity::from_flags(args.quiet, args.verbose);
+
+    if let Some(SkelCommand::Config {
+        command: ConfigCommand::Sources,
+    }) = args.command
+    {
+        return print_config_sources(
+            args.alt_config_path.as_deref().map(Path::new),
+            verbosity,
+        );
+    }
+
+    if let Some(SkelCommand::Sources {
+        command: SourcesCommand::Update,
+    }) = args.command
+    {
+        return print_source_updates(
+            args.alt_config_path.as_deref().map(Path::new),
+            verbosity,
+        );
+    }
+
+    if let Some(SkelCommand::Pack {
+        ref skeleton,
+        ref archive,
+    }) = args.command
+    {
+        return print_pack(
+            args.alt_config_path.as_deref().map(Path::new),
+            skeleton,
+            archive.as_deref().map(Path::new),
+            verbosity,
+        );
+    }
+
+    if let Some(SkelCommand::Install { ref path }) = args.command {
+        return print_install(
+            args.alt_config_path.as_deref().map(Path::new),
+            Path::new(path),
+            verbosity,
+        );
+    }
+
+    if let Some(SkelCommand::Completions { ref shell }) = args.command {
+        return print_completions(shell);
+    }
+
+    if let Some(SkelCommand::List { ref tag }) = args.command {
+        return print_skeletons(
+            args.alt_config_path.as_deref().map(Path::new),
+            tag.as_deref(),
+            verbosity,
+        );
+    }
 
-    let mut root_string = get_root(&args)?;
+    if args.pick {
+        let alt_config_path = args.alt_config_path.as_deref().map(Path::new);
 
-    let name = args
-        .name
-        .as_ref()
-        .ok_or("some how did not gate a name to use")?
-        .to_owned();
+        let picked = match pick_skeleton(alt_config_path, verbosity)? {
+            Some(picked) => picked,
+            None => return Err(SkelError::from("did not pick a skeleton")),
+        };
 
-    root_string.push(&name);
+        args.skeleton = Some(picked.key);
+        args.target = Some(ask_project_name()?);
 
-    if !args.dry_run && metadata(&root_string).is_ok() {
-        return Err(Box::from(format!(
-            "project exists {}",
-            root_string.as_os_str().to_str().unwrap()
-        )));
+        // the defaults are derived from the name the project will have
+        let name = make_generator(&args)?.project_name()?;
+
+        let answers = ask_vars(&picked.path, &name, &args.vars)?;
+
+        args.vars.extend(answers);
     }
 
-    let config = resolve_config(&args, root_string, name)?;
+    let generator = make_generator(&args)?;
+
+    match args.output {
+        Some(ref output) if !args.dry_run => {
+            let name = generator.project_name()?;
 
-    make_project_tree(args.dry_run, &config)
+            let mut archive = ArchiveOutput::create(Path::new(output), &name)?;
+
+            generator.generate_into(&mut archive)?;
+        }
+        _ => {
+            generator.generate()?;
+        }
+    }
+
+    Ok(())
 }
 
 /// this wraps `run()` so everything can be cleaned up before exiting with an error
 /// code
 fn main() {
+    // answer the shell when it is asking for completions
+    CompleteEnv::with_factory(SkelArgs::command).complete();
+
     if let Err(err) = run() {
-        eprintln!("{}", err);
+        eprintln!("{} {}", paint(Style::Error, Stream::Stderr, "error:"), err);
 
-        process::exit(1);
+        process::exit(err.exit_code());
     }
 
     process::exit(0);
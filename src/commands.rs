@@ -0,0 +1,190 @@
+use std::path::Path;
+
+use crate::{
+    config::{config_sources, load_main_config, update_sources},
+    error::SkelError,
+    pack::{install_skeleton, pack_skeleton},
+    report::{Reporter, Verbosity},
+    style::{paint, Stream, Style},
+};
+
+/// `skel config sources`, print every main config from lowest to highest
+/// priority, which config each skeleton is in and warn about skeletons that
+/// hide one in a lower layer
+pub fn print_config_sources(
+    alt_config_path: Option<&Path>,
+    verbosity: Verbosity,
+) -> Result<(), SkelError> {
+    let reporter = Reporter::new(verbosity, None);
+
+    let sources = config_sources(alt_config_path)?;
+    let (main_config, shadows) = load_main_config(alt_config_path, &reporter)?;
+
+    reporter.info("config files, lowest priority first");
+
+    for source in sources.iter() {
+        reporter.info(&format!(
+            "  {} {}",
+            paint(
+                Style::Kind,
+                Stream::Stdout,
+                &format!("{:<7}", source.layer.name())
+            ),
+            source.path.display()
+        ));
+    }
+
+    reporter.info("\nskeletons");
+
+    let mut skeletons: Vec<_> = main_config.skeletons.iter().collect();
+
+    skeletons.sort_by_key(|(name, _)| name.as_str());
+
+    for (name, skeleton) in skeletons {
+        reporter.info(&format!(
+            "  {} [{}] -> {}",
+            paint(Style::Kind, Stream::Stdout, name),
+            skeleton.aliases.join(", "),
+            skeleton.source.display()
+        ));
+
+        if let Some(ref description) = skeleton.description {
+            reporter.info(&format!("    {}", description));
+        }
+    }
+
+    for shadow in shadows.iter() {
+        reporter.warn(&format!(
+            "{} in {} shadows the one in {}",
+            paint(Style::Value, Stream::Stderr, &shadow.name),
+            shadow.path.display(),
+            shadow.shadowed.display()
+        ));
+    }
+
+    Ok(())
+}
+
+/// `skel list`, print every skeleton with its aliases and description, only
+/// the ones tagged with `tag` if it is given
+pub fn print_skeletons(
+    alt_config_path: Option<&Path>,
+    tag: Option<&str>,
+    verbosity: Verbosity,
+) -> Result<(), SkelError> {
+    let reporter = Reporter::new(verbosity, None);
+
+    let (main_config, _) = load_main_config(alt_config_path, &reporter)?;
+
+    let mut skeletons: Vec<_> = main_config
+        .skeletons
+        .iter()
+        .map(|(name, skeleton)| (name, skeleton, skeleton.meta()))
+        .filter(|(_, _, meta)| match tag {
+            Some(tag) => meta.tags.iter().flatten().any(|t| t == tag),
+            None => true,
+        })
+        .collect();
+
+    skeletons.sort_by_key(|(name, _, _)| name.as_str());
+
+    for (name, skeleton, meta) in skeletons {
+        let mut line = format!(
+            "{} [{}]",
+            paint(Style::Kind, Stream::Stdout, name),
+            skeleton.aliases.join(", ")
+        );
+
+        if let Some(ref version) = meta.version {
+            line.push_str(&format!(" {}", version));
+        }
+
+        if let Some(ref description) = meta.description {
+            line.push_str(&format!(" - {}", description));
+        }
+
+        reporter.info(&line);
+    }
+
+    Ok(())
+}
+
+/// `skel sources update`, fetch every git source and print the commit each is
+/// locked to now
+pub fn print_source_updates(
+    alt_config_path: Option<&Path>,
+    verbosity: Verbosity,
+) -> Result<(), SkelError> {
+    let reporter = Reporter::new(verbosity, None);
+
+    let mut checkouts = update_sources(alt_config_path, &reporter)?;
+
+    checkouts.sort_by(|a, b| a.name.cmp(&b.name));
+
+    if checkouts.is_empty() {
+        reporter.info("there are no git sources in the main configs");
+    }
+
+    for checkout in checkouts {
+        reporter.info(&format!(
+            "  {} {} -> {}",
+            paint(Style::Kind, Stream::Stdout, &checkout.name),
+            checkout.rev,
+            paint(Style::Value, Stream::Stdout, &checkout.commit)
+        ));
+    }
+
+    Ok(())
+}
+
+/// `skel pack`, pack a skeleton and print where the archive was written
+pub fn print_pack(
+    alt_config_path: Option<&Path>,
+    skeleton: &str,
+    archive: Option<&Path>,
+    verbosity: Verbosity,
+) -> Result<(), SkelError> {
+    let reporter = Reporter::new(verbosity, None);
+
+    let packed = pack_skeleton(alt_config_path, skeleton, archive, &reporter)?;
+
+    reporter.info(&format!(
+        "packed {} in to {}",
+        paint(Style::Kind, Stream::Stdout, &packed.name),
+        paint(
+            Style::Value,
+            Stream::Stdout,
+            &packed.archive.to_string_lossy()
+        )
+    ));
+
+    Ok(())
+}
+
+/// `skel install`, install a packed skeleton and print where it went
+pub fn print_install(
+    alt_config_path: Option<&Path>,
+    path: &Path,
+    verbosity: Verbosity,
+) -> Result<(), SkelError> {
+    let reporter = Reporter::new(verbosity, None);
+
+    let installed = install_skeleton(alt_config_path, path, &reporter)?;
+
+    reporter.info(&format!(
+        "installed {} in to {} and added it to {}",
+        paint(Style::Kind, Stream::Stdout, &installed.name),
+        paint(
+            Style::Value,
+            Stream::Stdout,
+            &installed.dir.to_string_lossy()
+        ),
+        paint(
+            Style::Value,
+            Stream::Stdout,
+            &installed.config_path.to_string_lossy()
+        )
+    ));
+
+    Ok(())
+}
@@ -1,29 +1,142 @@
 use std::{
-    collections::HashMap,
+    cell::OnceCell,
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
     env,
     error::Error,
+    ffi::OsString,
     fs,
     path::{Path, PathBuf},
-    rc::Rc,
 };
 
-use handlebars::Handlebars;
+use regex::Regex;
 
 use serde::Deserialize;
 
-use crate::{parse_args::SkelArgs, templating::instantiate_handlebars};
+use crate::{
+    error::SkelError,
+    generator::Generator,
+    parse_args::COMMAND_NAMES,
+    report::Reporter,
+    sources::{
+        cache_dir, checkout_sources, lock_path, resolve_git_url, Checkout,
+        GitSource, SourceMode,
+    },
+    style::{paint, Stream, Style},
+    templating::{engine_from_source, Engine, EngineKind, HandlebarsEngine},
+};
 
 /// the path and alias to find a skeleton file
-#[derive(Deserialize, Debug)]
+///
+/// a relative path is relative to the dir of the main config it is in, or to
+/// the checkout of the git source named in `source`
+#[derive(Deserialize, Debug, Default)]
 pub struct Skeleton {
     pub path: String,
+    /// the git source in `[sources]` the path is in
+    #[serde(rename = "source")]
+    pub git_source: Option<String>,
+    #[serde(default)]
     pub aliases: Vec<String>,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// the main config this skeleton is in
+    #[serde(skip)]
+    pub source: PathBuf,
+    /// the `[meta]` read from the skeleton file, read once when first needed
+    #[serde(skip)]
+    pub(crate) file_meta: OnceCell<SkelMeta>,
+}
+
+impl Skeleton {
+    /// the `[meta]` of the skeleton file with the description and tags from
+    /// the main config added, the main config description wins
+    pub fn meta(&self) -> SkelMeta {
+        let mut meta = self
+            .file_meta
+            .get_or_init(|| {
+                read_meta(Path::new(&self.path)).unwrap_or_default()
+            })
+            .clone();
+
+        if self.description.is_some() {
+            meta.description = self.description.clone();
+        }
+
+        let mut tags = self.tags.clone();
+
+        for tag in meta.tags.iter().flatten() {
+            if !tags.contains(tag) {
+                tags.push(tag.clone());
+            }
+        }
+
+        meta.tags = Some(tags);
+
+        meta
+    }
 }
 
 /// this is them main config for the program
-#[derive(Deserialize, Debug)]
+///
+/// `skeleton_dirs` are searched for skeletons and `include` reads other main
+/// configs, relative paths in both are relative to the dir of this config
+///
+/// `ignore_case` matches skeleton names and aliases without case and
+/// `prefix_match` finds a skeleton from the start of its name or an alias when
+/// only one skeleton starts with it
+#[derive(Deserialize, Debug, Default)]
 pub struct MainConfig {
+    #[serde(default)]
     pub skeletons: HashMap<String, Skeleton>,
+    pub skeleton_dirs: Option<Vec<String>>,
+    pub include: Option<Vec<String>>,
+    pub ignore_case: Option<bool>,
+    pub prefix_match: Option<bool>,
+    #[serde(default)]
+    pub sources: HashMap<String, GitSource>,
+}
+
+impl MainConfig {
+    // take the settings that are set in a config read later
+    fn merge_settings(&mut self, other: &MainConfig) {
+        if other.ignore_case.is_some() {
+            self.ignore_case = other.ignore_case;
+        }
+
+        if other.prefix_match.is_some() {
+            self.prefix_match = other.prefix_match;
+        }
+    }
+
+    fn ignore_case(&self) -> bool {
+        self.ignore_case.unwrap_or(false)
+    }
+
+    // the name as it is matched, lower case with `ignore_case`
+    fn match_name(&self, name: &str) -> String {
+        if self.ignore_case() {
+            name.to_lowercase()
+        } else {
+            name.to_string()
+        }
+    }
+}
+
+/// what a skeleton says about itself in its `[meta]` table
+///
+/// the aliases are only used when it is found in a `skeleton_dirs` dir and skel
+/// refuses to make a skeleton that needs a newer `min_skel_version`
+#[derive(Deserialize, Default, Clone, Debug)]
+pub struct SkelMeta {
+    pub aliases: Option<Vec<String>>,
+    pub description: Option<String>,
+    pub tags: Option<Vec<String>>,
+    pub version: Option<String>,
+    pub authors: Option<Vec<String>>,
+    pub homepage: Option<String>,
+    pub min_skel_version: Option<String>,
 }
 
 /// a file template
@@ -32,45 +145,180 @@ pub struct SkelTemplate {
     pub path: String,
     pub template: Option<String>,
     pub include: Option<String>,
+    /// let this template replace an earlier one with the same path
+    pub r#override: Option<bool>,
+}
+
+/// a file or dir to copy in to the project as is
+#[derive(Deserialize, Default)]
+pub struct SkelCopy {
+    pub from: String,
+    pub to: String,
+}
+
+/// a single step in the `steps` pipeline
+///
+/// only one of `dir`, `file`, `template`, `copy` or `run` should be given,
+/// `capture` binds the output of a `run` step to a variable for later steps
+#[derive(Deserialize, Default)]
+pub struct SkelStep {
+    pub dir: Option<String>,
+    pub file: Option<String>,
+    pub template: Option<SkelTemplate>,
+    pub copy: Option<SkelCopy>,
+    pub run: Option<String>,
+    pub capture: Option<String>,
+}
+
+/// a text edit to a file that is already in the project, most likely made by
+/// the build script
+///
+/// only one of `append`, `prepend`, `insert_after`, `insert_before`, `replace`
+/// or `apply` should be given, `insert_after`, `insert_before` and `replace`
+/// are regexes and use `content` as the new text
+#[derive(Deserialize, Default)]
+pub struct SkelEdit {
+    pub path: String,
+    pub append: Option<String>,
+    pub prepend: Option<String>,
+    pub insert_after: Option<String>,
+    pub insert_before: Option<String>,
+    pub replace: Option<String>,
+    pub content: Option<String>,
+    pub apply: Option<String>,
+    pub optional: Option<bool>,
+}
+
+/// a fragment to deep merge in to a toml, json or yaml file in the project
+///
+/// the format is taken from the file extension unless `format` is given,
+/// `arrays` can be `append` (the default) or `replace`
+#[derive(Deserialize, Default)]
+pub struct SkelMerge {
+    pub path: String,
+    pub format: Option<String>,
+    pub content: Option<String>,
+    pub include: Option<String>,
+    pub arrays: Option<String>,
+    pub optional: Option<bool>,
+}
+
+/// how the build script and run steps are run
+///
+/// `clear_env` starts the scripts with an empty environment except for the
+/// variables in `env_allow`, `timeout` is in seconds and `limits` is a
+/// resource limit profile of `none`, `standard` or `strict`
+#[derive(Deserialize, Default, Clone, Debug)]
+pub struct SkelExec {
+    pub clear_env: Option<bool>,
+    pub env_allow: Option<Vec<String>>,
+    pub timeout: Option<u64>,
+    pub umask: Option<String>,
+    pub limits: Option<String>,
+    /// the dir scripts are run in, the current dir by default
+    #[serde(skip)]
+    pub work_dir: Option<PathBuf>,
 }
 
 /// a skeleton
 #[derive(Deserialize, Default)]
 pub struct SkelConfig {
+    pub meta: Option<SkelMeta>,
+    /// the template engine, handlebars by default
+    pub engine: Option<EngineKind>,
+    /// a regex the whole project name has to match
+    pub name_pattern: Option<String>,
+    /// shown when the name does not match `name_pattern`
+    pub name_hint: Option<String>,
+    /// variables templated from the other variables like
+    /// `crate_name = "{{snake_case name}}"`
+    pub derived: Option<BTreeMap<String, String>>,
     pub dirs: Option<Vec<String>>,
     pub files: Option<Vec<String>>,
     pub templates: Option<Vec<SkelTemplate>>,
     pub build: Option<String>,
     pub build_first: Option<bool>,
+    pub steps: Option<Vec<SkelStep>>,
+    pub edits: Option<Vec<SkelEdit>>,
+    pub merge: Option<Vec<SkelMerge>>,
+    pub exec: Option<SkelExec>,
+    /// the build script before templating
+    #[serde(skip)]
+    pub raw_build: Option<String>,
+    /// the derived variables before templating
+    #[serde(skip)]
+    pub raw_derived: Option<BTreeMap<String, String>>,
+}
+
+// the parts of the skeleton that are needed before templating
+//
+// steps are templated as they run so they can use variables captured by
+// earlier steps, the build script is used to tell if the script has changed
+// since it was trusted, the name is checked and the derived variables are
+// added before the rest of the skeleton is templated
+#[derive(Deserialize)]
+struct RawSkelConfig {
+    build: Option<String>,
+    steps: Option<Vec<SkelStep>>,
+    name_pattern: Option<String>,
+    name_hint: Option<String>,
+    derived: Option<BTreeMap<String, String>>,
 }
 
+// the path shown for a skeleton given as text, it is in the current dir so
+// `skel-dir` is the current dir
+const STDIN_SKELETON: &str = "<stdin>";
+
+// the variables every skeleton gets, these can not be replaced
+const BUILT_IN_VARS: [&str; 4] = ["name", "root", "config-dir", "skel-dir"];
+
 /// the needed data to make the project
 #[derive(Default)]
-pub struct RunConfig<'reg> {
+pub struct RunConfig {
     pub skel_conf: SkelConfig,
+    pub skel_path: PathBuf,
     pub root_path: PathBuf,
     pub template_data: HashMap<String, String>,
-    pub handle: Handlebars<'reg>,
+    pub engine: Box<dyn Engine>,
+    pub no_build: bool,
+    pub trust: bool,
+    pub exec: SkelExec,
+    pub reporter: Reporter,
 }
 
-// a struct to hold duplicate values in a main config
+// the names two skeletons both use, the keys are the same when a skeleton
+// repeats an alias
 struct Duplicate<'a> {
     key_1: &'a str,
     key_2: &'a str,
-    alias: Vec<&'a str>,
+    names: Vec<&'a str>,
+}
+
+// a key and the config it is in if it is known
+fn owner(key: &str, skeleton: &Skeleton) -> String {
+    let key = paint(Style::Value, Stream::Stderr, key);
+
+    if skeleton.source.as_os_str().is_empty() {
+        key
+    } else {
+        format!("{} ({})", key, skeleton.source.display())
+    }
 }
 
-fn make_duplicate_err_msg(duplicates: &[Duplicate]) -> String {
+fn make_duplicate_err_msg(
+    duplicates: &[Duplicate],
+    config: &MainConfig,
+) -> String {
     let mut dup_str = String::from("duplicate keys or aliases found\n");
     let duplicates_len = duplicates.len() - 1;
 
     for (i, dup) in duplicates.iter().enumerate() {
         let new_dup = format!(
-                "keys [\x1b[31m{}\x1b[0m, \x1b[31m{}\x1b[0m]\n    alias: [\x1b[31m{}\x1b[0m]",
-                dup.key_1,
-                dup.key_2,
-                dup.alias.join(", ")
-            );
+            "keys [{}, {}]\n    names: [{}]",
+            owner(dup.key_1, &config.skeletons[dup.key_1]),
+            owner(dup.key_2, &config.skeletons[dup.key_2]),
+            paint(Style::Value, Stream::Stderr, &dup.names.join(", "))
+        );
 
         dup_str.push_str(&new_dup);
 
@@ -83,64 +331,106 @@ fn make_duplicate_err_msg(duplicates: &[Duplicate]) -> String {
     dup_str
 }
 
-// check for duplicates in the main config
+// a skeleton named after a command could never be made, the command would run
+fn check_command_names(
+    config: &MainConfig,
+    keys: &[&String],
+) -> Result<(), Box<dyn Error>> {
+    let mut found = vec![];
+
+    for key in keys.iter() {
+        let skeleton = &config.skeletons[key.as_str()];
+
+        let names: Vec<&str> = std::iter::once(key.as_str())
+            .chain(skeleton.aliases.iter().map(String::as_str))
+            .filter(|name| COMMAND_NAMES.contains(name))
+            .collect();
+
+        if !names.is_empty() {
+            found.push(format!(
+                "  key {}\n    names: [{}]",
+                owner(key, skeleton),
+                paint(Style::Value, Stream::Stderr, &names.join(", "))
+            ));
+        }
+    }
+
+    if found.is_empty() {
+        Ok(())
+    } else {
+        Err(Box::from(format!(
+            "skeleton names can't be skel commands\n{}",
+            found.join("\n")
+        )))
+    }
+}
+
+// check that every key and alias in the main config finds one skeleton
 //
-// NOTE: it would be more efficient to check for the target skeleton in this
-// function but whatever
-fn check_config(config: &MainConfig) -> Result<(), Box<dyn Error>> {
-    // collect all the skeletons in to a vec so we can iterate over them in
-    // order, this is kinda bad but it is also the easiest
-    let key_alias: Vec<(&String, &Skeleton)> =
-        config.skeletons.iter().collect();
-
-    let mut duplicates = vec![];
-
-    // iterate over all the skeletons in the main config then iterate over the
-    // following ones checking if there any duplicates
-    //
-    // we can skip past the previous skeletons in the inner loop as the
-    // previous skeletons have already been checked for duplicates
-    for (i, (key_1, skeleton_1)) in key_alias.iter().enumerate() {
-        for (key_2, skeleton_2) in key_alias.iter().skip(i + 1) {
-            let mut duplicate: Option<Duplicate> = None;
-
-            // NOTE: i guess multiple keys will be a parsing error
-            if key_1 == key_2 {
-                let dup = Duplicate {
-                    key_1,
-                    key_2,
-                    alias: vec![],
-                };
-
-                duplicate = Some(dup);
+// an alias can't be another skeleton's key or alias and can't be repeated in
+// one skeleton, with `ignore_case` names that only differ in case are the same
+// name. an alias that is the skeleton's own key is fine
+pub(crate) fn check_config(config: &MainConfig) -> Result<(), Box<dyn Error>> {
+    // sort the keys so the errors come out in the same order every time
+    let mut keys: Vec<&String> = config.skeletons.keys().collect();
+
+    keys.sort();
+
+    check_command_names(config, &keys)?;
+
+    // every name that is matched and the key that owns it
+    let mut namespace: HashMap<String, &str> = HashMap::new();
+
+    // the duplicate names of each pair of keys
+    let mut duplicates: BTreeMap<(&str, &str), Vec<&str>> = BTreeMap::new();
+
+    for key in keys.iter() {
+        if let Some(other) = namespace.insert(config.match_name(key), key) {
+            duplicates.entry((other, key)).or_default().push(key);
+        }
+    }
+
+    for key in keys.iter() {
+        let own_name = config.match_name(key);
+        let mut seen = vec![];
+
+        for alias in config.skeletons[key.as_str()].aliases.iter() {
+            let name = config.match_name(alias);
+
+            if name == own_name {
+                continue;
             }
 
-            // iterate over the outer value's aliases and check if any are in
-            // the inside value
-            for s in skeleton_1.aliases.iter() {
-                if skeleton_2.aliases.contains(s) {
-                    if let Some(ref mut duplicate) = duplicate {
-                        duplicate.alias.push(s);
-                    } else {
-                        let dup = Duplicate {
-                            key_1,
-                            key_2,
-                            alias: vec![s],
-                        };
-
-                        duplicate = Some(dup);
-                    }
-                }
+            if seen.contains(&name) {
+                duplicates.entry((key, key)).or_default().push(alias);
+
+                continue;
             }
 
-            if let Some(duplicate) = duplicate {
-                duplicates.push(duplicate);
+            match namespace.get(&name) {
+                Some(other) => {
+                    duplicates.entry((other, key)).or_default().push(alias)
+                }
+                None => {
+                    namespace.insert(name.clone(), key);
+                }
             }
+
+            seen.push(name);
         }
     }
 
     if !duplicates.is_empty() {
-        let dup_err_msg = make_duplicate_err_msg(&duplicates);
+        let duplicates: Vec<Duplicate> = duplicates
+            .into_iter()
+            .map(|((key_1, key_2), names)| Duplicate {
+                key_1,
+                key_2,
+                names,
+            })
+            .collect();
+
+        let dup_err_msg = make_duplicate_err_msg(&duplicates, config);
 
         Err(Box::from(dup_err_msg))
     } else {
@@ -148,342 +438,1861 @@ fn check_config(config: &MainConfig) -> Result<(), Box<dyn Error>> {
     }
 }
 
-// get that path to the main config
-fn get_main_config_path(args: &SkelArgs) -> Result<PathBuf, Box<dyn Error>> {
-    // first check if an alternate config path is given
-    let main_config_path = if let Some(ref config_string) = args.alt_config_path
+/// where a main config was found, from lowest to highest priority
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConfigLayer {
+    /// `skel/config.toml` in `$XDG_CONFIG_DIRS`, `/etc/xdg` by default
+    System,
+    /// `$XDG_CONFIG_HOME/skel/config.toml`
+    User,
+    /// `.skel/config.toml` in the current dir or the closest parent
+    Project,
+    /// the file in `SKEL_CONFIG`
+    Env,
+    /// the file given with `--alt-config-path`, no other config is read
+    Alt,
+}
+
+impl ConfigLayer {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConfigLayer::System => "system",
+            ConfigLayer::User => "user",
+            ConfigLayer::Project => "project",
+            ConfigLayer::Env => "env",
+            ConfigLayer::Alt => "alt",
+        }
+    }
+}
+
+/// a main config file and the layer it is in
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigSource {
+    pub layer: ConfigLayer,
+    pub path: PathBuf,
+}
+
+/// a skeleton in a higher layer that hides one with the same name in a lower
+/// layer
+#[derive(Clone, Debug)]
+pub struct Shadow {
+    pub name: String,
+    pub path: PathBuf,
+    pub shadowed: PathBuf,
+}
+
+// the user config, this is fine on linux and probably mac but will fail on
+// windows
+pub(crate) fn user_config_path(
+    var: &dyn Fn(&str) -> Option<OsString>,
+) -> Option<PathBuf> {
+    let xdg_config = match var("XDG_CONFIG_HOME") {
+        Some(path) if !path.is_empty() => PathBuf::from(path),
+        _ => PathBuf::from(var("HOME")?).join(".config"),
+    };
+
+    Some(xdg_config.join("skel/config.toml"))
+}
+
+// every main config that exists from lowest to highest priority
+fn find_config_sources(
+    var: &dyn Fn(&str) -> Option<OsString>,
+    current_dir: &Path,
+) -> Result<Vec<ConfigSource>, Box<dyn Error>> {
+    let mut candidates = vec![];
+
+    // the first dir in `XDG_CONFIG_DIRS` is the most important
+    let config_dirs = var("XDG_CONFIG_DIRS")
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| OsString::from("/etc/xdg"));
+
+    let mut system_dirs: Vec<PathBuf> = env::split_paths(&config_dirs)
+        .filter(|dir| dir.is_absolute())
+        .collect();
+
+    system_dirs.reverse();
+
+    for dir in system_dirs {
+        candidates.push((ConfigLayer::System, dir.join("skel/config.toml")));
+    }
+
+    if let Some(path) = user_config_path(var) {
+        candidates.push((ConfigLayer::User, path));
+    }
+
+    if let Some(path) = current_dir
+        .ancestors()
+        .map(|dir| dir.join(".skel/config.toml"))
+        .find(|path| path.is_file())
     {
-        PathBuf::from(config_string)
+        candidates.push((ConfigLayer::Project, path));
+    }
 
-    // else try and use xdg_config_home
-    } else {
-        let mut xdg_config = match env::var("XDG_CONFIG_HOME") {
-            Ok(path) => PathBuf::from(path),
-            _ => {
-                // this is fine on linux and probably mac
-                // but will fail on windows
-                let home = env::var("HOME")?;
+    let mut sources: Vec<ConfigSource> = candidates
+        .into_iter()
+        .filter(|(_, path)| path.is_file())
+        .map(|(layer, path)| ConfigSource { layer, path })
+        .collect();
 
-                let mut home_path = PathBuf::from(home);
+    // a config that was asked for has to exist
+    if let Some(path) = var("SKEL_CONFIG").filter(|path| !path.is_empty()) {
+        let path = PathBuf::from(path);
 
-                home_path.push(".config");
+        if !path.is_file() {
+            return Err(Box::new(SkelError::ConfigNotFound { path }));
+        }
 
-                home_path
-            }
+        sources.push(ConfigSource {
+            layer: ConfigLayer::Env,
+            path,
+        });
+    }
+
+    Ok(sources)
+}
+
+/// every main config that will be read from lowest to highest priority
+///
+/// with `alt_config_path` only that file is used, it is an error if no
+/// config is found
+pub fn config_sources(
+    alt_config_path: Option<&Path>,
+) -> Result<Vec<ConfigSource>, SkelError> {
+    if let Some(path) = alt_config_path {
+        return if path.is_file() {
+            Ok(vec![ConfigSource {
+                layer: ConfigLayer::Alt,
+                path: path.to_path_buf(),
+            }])
+        } else {
+            Err(SkelError::ConfigNotFound {
+                path: path.to_path_buf(),
+            })
         };
+    }
 
-        xdg_config.push("skel");
+    let var = |key: &str| env::var_os(key);
 
-        xdg_config.push("config.toml");
+    let current_dir =
+        env::current_dir().map_err(|e| SkelError::io(Path::new("."), e))?;
 
-        xdg_config
-    };
+    let sources = find_config_sources(&var, &current_dir)?;
 
-    if main_config_path.is_file() {
-        Ok(main_config_path)
+    if sources.is_empty() {
+        Err(SkelError::ConfigNotFound {
+            path: user_config_path(&var)
+                .unwrap_or_else(|| PathBuf::from("skel/config.toml")),
+        })
     } else {
-        Err(Box::from(format!(
-            "given config path does not exist or is not a file {}",
-            main_config_path.display()
-        )))
+        Ok(sources)
     }
 }
 
-// get the main config file from a given path and return it
-fn get_main_config(
-    main_config_path: &Path,
-    handle: &Handlebars,
-    template_data: &HashMap<String, String>,
-) -> Result<MainConfig, Box<dyn Error>> {
-    let config_string = fs::read_to_string(main_config_path)?;
+// only the `[meta]` of a skeleton
+#[derive(Deserialize)]
+struct MetaOnly {
+    meta: Option<SkelMeta>,
+}
 
-    let templated_config_string =
-        handle.render_template(&config_string, &template_data)?;
+// the `[meta]` of a skeleton before templating, the error says why a skeleton
+// that can't be read or is not valid toml before templating has no meta
+fn read_meta(skel_path: &Path) -> Result<SkelMeta, String> {
+    let text = fs::read_to_string(skel_path).map_err(|e| e.to_string())?;
 
-    let config: MainConfig = toml::from_str(&templated_config_string)?;
+    toml::from_str::<MetaOnly>(&text)
+        .map(|meta_only| meta_only.meta.unwrap_or_default())
+        .map_err(|e| e.message().to_string())
+}
 
-    check_config(&config)?;
+fn raw_meta(skel_config_buf: &str) -> Option<SkelMeta> {
+    toml::from_str::<MetaOnly>(skel_config_buf)
+        .ok()
+        .and_then(|meta_only| meta_only.meta)
+}
 
-    Ok(config)
+// a version like `0.2.1-beta.1`, build metadata after a `+` is left out
+#[derive(Debug, PartialEq)]
+struct Version {
+    numbers: Vec<u64>,
+    pre: Vec<String>,
 }
 
-// retrieve the skeleton path from the main config
-fn skeleton_path_from_config(
-    target: &str,
-    main_config: &MainConfig,
-) -> Result<String, Box<dyn Error>> {
-    let skel_config_path =
-        if let Some(skeleton) = main_config.skeletons.get(target) {
-            Some(skeleton.path.clone())
+fn parse_version(version: &str) -> Option<Version> {
+    let version = version.trim().split('+').next()?;
+
+    let (numbers, pre) = match version.split_once('-') {
+        Some((numbers, pre)) => (numbers, Some(pre)),
+        None => (version, None),
+    };
+
+    let numbers = numbers
+        .split('.')
+        .map(|part| part.parse().ok())
+        .collect::<Option<Vec<u64>>>()?;
+
+    let pre: Vec<String> = match pre {
+        Some(pre) => pre.split('.').map(String::from).collect(),
+        None => vec![],
+    };
+
+    if pre.iter().any(|part| part.is_empty()) {
+        return None;
+    }
+
+    Some(Version { numbers, pre })
+}
+
+// compare versions like semver, missing numbers are 0 so `0.1.1.0` is
+// `0.1.1` and a pre-release comes before the release it is for
+fn compare_versions(a: &Version, b: &Version) -> Ordering {
+    let len = a.numbers.len().max(b.numbers.len());
+    let number = |v: &Version, i: usize| v.numbers.get(i).copied().unwrap_or(0);
+
+    let numbers = (0..len)
+        .map(|i| number(a, i).cmp(&number(b, i)))
+        .find(|ord| ord.is_ne())
+        .unwrap_or(Ordering::Equal);
+
+    if numbers.is_ne() {
+        return numbers;
+    }
+
+    match (a.pre.is_empty(), b.pre.is_empty()) {
+        (true, true) => return Ordering::Equal,
+        (true, false) => return Ordering::Greater,
+        (false, true) => return Ordering::Less,
+        (false, false) => {}
+    }
+
+    // numeric parts compare as numbers and come before words
+    for (x, y) in a.pre.iter().zip(b.pre.iter()) {
+        let ord = match (x.parse::<u64>(), y.parse::<u64>()) {
+            (Ok(x), Ok(y)) => x.cmp(&y),
+            (Ok(_), Err(_)) => Ordering::Less,
+            (Err(_), Ok(_)) => Ordering::Greater,
+            (Err(_), Err(_)) => x.cmp(y),
+        };
+
+        if ord.is_ne() {
+            return ord;
+        }
+    }
+
+    a.pre.len().cmp(&b.pre.len())
+}
+
+// refuse a skeleton that needs a newer skel than this one
+fn check_skel_version(
+    skel_config_path: &Path,
+    meta: Option<&SkelMeta>,
+) -> Result<(), Box<dyn Error>> {
+    let required = match meta.and_then(|meta| meta.min_skel_version.as_ref()) {
+        Some(required) => required,
+        None => return Ok(()),
+    };
+
+    let current = env!("CARGO_PKG_VERSION");
+
+    let required_version = parse_version(required).ok_or_else(|| {
+        format!(
+            "{} min_skel_version {} is not a valid version",
+            skel_config_path.display(),
+            required
+        )
+    })?;
+
+    let current_version =
+        parse_version(current).expect("skel has a bad version some how");
+
+    if compare_versions(&current_version, &required_version).is_lt() {
+        return Err(Box::from(format!(
+            "{} needs skel {} or newer, this is skel {}",
+            skel_config_path.display(),
+            paint(Style::Value, Stream::Stderr, required),
+            current
+        )));
+    }
+
+    Ok(())
+}
+
+// find the skeletons in a `skeleton_dirs` dir, a `*.toml` file or a dir with a
+// `skeleton.toml` in it, the key is the file or dir name and the aliases and
+// description come from the `[meta]` in the skeleton
+fn discover_skeletons(
+    dir: &Path,
+    source: &Path,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<(String, Skeleton)>, Box<dyn Error>> {
+    // a dir that does not exist yet has no skeletons
+    if !dir.is_dir() {
+        return Ok(vec![]);
+    }
+
+    let mut paths = fs::read_dir(dir)
+        .map_err(|e| SkelError::io(dir, e))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<PathBuf>, _>>()
+        .map_err(|e| SkelError::io(dir, e))?;
+
+    paths.sort();
+
+    let mut skeletons = vec![];
+
+    for path in paths {
+        let (name, skel_path) = if path.join("skeleton.toml").is_file() {
+            (path.file_name(), path.join("skeleton.toml"))
+        } else if path.is_file()
+            && path.extension().is_some_and(|ext| ext == "toml")
+        {
+            (path.file_stem(), path.clone())
         } else {
-            let mut skel_path = None;
+            continue;
+        };
+
+        let name = match name {
+            Some(name) => name.to_string_lossy().to_string(),
+            None => continue,
+        };
+
+        // the skeleton can still be made, it is only found by its name
+        let meta = read_meta(&skel_path).unwrap_or_else(|e| {
+            warnings.push(format!(
+                "can not read the [meta] of {} before templating so it has no \
+                aliases or description, {}",
+                skel_path.display(),
+                e
+            ));
+
+            SkelMeta::default()
+        });
+
+        skeletons.push((
+            name,
+            Skeleton {
+                path: skel_path.to_string_lossy().to_string(),
+                aliases: meta.aliases.clone().unwrap_or_default(),
+                description: meta.description.clone(),
+                tags: meta.tags.clone().unwrap_or_default(),
+                source: source.to_path_buf(),
+                file_meta: OnceCell::from(meta),
+                ..Default::default()
+            },
+        ));
+    }
+
+    Ok(skeletons)
+}
+
+// what is kept while reading a main config and everything it includes
+#[derive(Default)]
+struct ReadState {
+    // the configs being read, to find a config that includes itself
+    including: Vec<PathBuf>,
+    // the dir git sources are kept in, `$XDG_CACHE_HOME/skel` when `None`
+    cache_dir: Option<PathBuf>,
+    // only use the cache, fetch what is missing or fetch every source again
+    sources: SourceMode,
+    // every git source that was checked out
+    checkouts: Vec<Checkout>,
+    // problems that do not stop the configs from being read
+    warnings: Vec<String>,
+}
+
+// read a main config with everything it includes and discovers in the order
+// they are merged, a later skeleton replaces an earlier one with the same name
+//
+// included configs come first then discovered skeletons then the ones in
+// `[skeletons]`, each config is templated with its own dir as `config-dir`.
+// the settings of each config are merged in to `settings` in the same order
+fn read_config_tree(
+    config_path: &Path,
+    engine: &dyn Engine,
+    template_data: &HashMap<String, String>,
+    state: &mut ReadState,
+    settings: &mut MainConfig,
+) -> Result<Vec<(String, Skeleton)>, Box<dyn Error>> {
+    let canonical = fs::canonicalize(config_path)
+        .unwrap_or_else(|_| config_path.to_path_buf());
+
+    if state.including.contains(&canonical) {
+        return Err(Box::from(format!(
+            "{} includes itself",
+            config_path.display()
+        )));
+    }
+
+    let config_dir = config_path.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut config_data = template_data.clone();
+
+    config_data.insert(
+        "config-dir".to_string(),
+        config_dir.to_string_lossy().to_string(),
+    );
+
+    let mut config = get_main_config(config_path, engine, &config_data)?;
+
+    for source in config.sources.values_mut() {
+        source.git = resolve_git_url(&source.git, config_dir);
+    }
+
+    let checkouts = if config.sources.is_empty() {
+        HashMap::new()
+    } else {
+        let cache_dir = match state.cache_dir {
+            Some(ref cache_dir) => cache_dir.clone(),
+            None => cache_dir()?,
+        };
+
+        checkout_sources(
+            &config.sources,
+            &lock_path(config_path),
+            &cache_dir,
+            state.sources,
+            &mut state.warnings,
+        )?
+    };
+
+    state.checkouts.extend(checkouts.values().cloned());
+
+    let mut skeletons = vec![];
+
+    state.including.push(canonical);
+
+    for include in config.include.iter().flatten() {
+        let include_path = config_dir.join(include);
+
+        if !include_path.is_file() {
+            return Err(Box::new(SkelError::ConfigNotFound {
+                path: include_path,
+            }));
+        }
 
-            // check all the aliases to see if one matches
-            for (_, skeleton) in main_config.skeletons.iter() {
-                if skeleton.aliases.iter().any(|s| s == target) {
-                    skel_path = Some(skeleton.path.clone());
+        skeletons.extend(read_config_tree(
+            &include_path,
+            engine,
+            template_data,
+            state,
+            settings,
+        )?);
+    }
+
+    state.including.pop();
+
+    settings.merge_settings(&config);
+
+    for dir in config.skeleton_dirs.iter().flatten() {
+        skeletons.extend(discover_skeletons(
+            &config_dir.join(dir),
+            config_path,
+            &mut state.warnings,
+        )?);
+    }
 
-                    break;
+    let mut declared: Vec<(String, Skeleton)> =
+        config.skeletons.into_iter().collect();
+
+    declared.sort_by(|a, b| a.0.cmp(&b.0));
+
+    for (name, mut skeleton) in declared {
+        let base = match skeleton.git_source {
+            Some(ref source) => match checkouts.get(source) {
+                Some(checkout) => checkout.dir.as_path(),
+                // a source that is not in the cache yet was warned about
+                None if config.sources.contains_key(source) => continue,
+                None => {
+                    return Err(Box::from(format!(
+                        "skeleton {} is in source {} that is not in {}",
+                        name,
+                        source,
+                        config_path.display()
+                    )))
                 }
+            },
+            None => config_dir,
+        };
+
+        skeleton.path = base.join(&skeleton.path).to_string_lossy().to_string();
+
+        skeleton.source = config_path.to_path_buf();
+
+        skeletons.push((name, skeleton));
+    }
+
+    Ok(skeletons)
+}
+
+// read every main config and merge them in to one, a skeleton in a higher
+// layer replaces one with the same name
+//
+// each config is templated with its own dir as `config-dir`
+fn merge_main_configs(
+    sources: &[ConfigSource],
+    engine: &dyn Engine,
+    template_data: &HashMap<String, String>,
+    state: &mut ReadState,
+) -> Result<(MainConfig, Vec<Shadow>), Box<dyn Error>> {
+    let mut merged = MainConfig::default();
+
+    let mut shadows = vec![];
+
+    for source in sources.iter() {
+        let skeletons = read_config_tree(
+            &source.path,
+            engine,
+            template_data,
+            state,
+            &mut merged,
+        )?;
+
+        for (name, skeleton) in skeletons {
+            let path = skeleton.source.clone();
+
+            if let Some(old) = merged.skeletons.insert(name.clone(), skeleton) {
+                shadows.push(Shadow {
+                    name,
+                    path,
+                    shadowed: old.source,
+                });
             }
+        }
+    }
 
-            skel_path
-        };
+    check_config(&merged)?;
 
-    skel_config_path.ok_or_else(|| {
-        Box::from(format!(
-            "did not find matching skeleton or alias for {}",
-            target
-        ))
-    })
+    Ok((merged, shadows))
 }
 
-// find the skeleton config path and check if the file exists
-fn get_skeleton_config_path(
-    args: &SkelArgs,
-    main_config: &MainConfig,
-) -> Result<PathBuf, Box<dyn Error>> {
-    // a file given on the cli
-    let skel_path = if let Some(skeleton_file) = args.skeleton_file.as_ref() {
-        let skel_path = PathBuf::from(skeleton_file);
+/// read and merge the main configs from every layer, see `config_sources`
+///
+/// the configs are templated with only `config-dir`, anything wrong that does
+/// not stop the configs from being read is a warning on the reporter. git
+/// sources are only read from the cache, nothing is fetched or locked
+pub fn load_main_config(
+    alt_config_path: Option<&Path>,
+    reporter: &Reporter,
+) -> Result<(MainConfig, Vec<Shadow>), SkelError> {
+    let sources = config_sources(alt_config_path)?;
+
+    let mut state = ReadState::default();
+
+    let loaded = merge_main_configs(
+        &sources,
+        &HandlebarsEngine::new(),
+        &HashMap::new(),
+        &mut state,
+    )?;
+
+    for warning in state.warnings {
+        reporter.warn(&warning);
+    }
 
-        skel_path
-    // a skeleton project or alias
-    } else if let Some(target) = args.skeleton.as_ref() {
-        let skel_path = skeleton_path_from_config(target, main_config)?;
+    Ok(loaded)
+}
 
-        PathBuf::from(skel_path)
-    } else {
-        return Err(Box::from(String::from(
-            "did not get skeleton to make some how",
-        )));
+/// fetch every git source in the main configs again and lock each to the
+/// commit its rev points to now
+pub fn update_sources(
+    alt_config_path: Option<&Path>,
+    reporter: &Reporter,
+) -> Result<Vec<Checkout>, SkelError> {
+    let sources = config_sources(alt_config_path)?;
+
+    let mut state = ReadState {
+        sources: SourceMode::Update,
+        ..Default::default()
     };
 
-    if skel_path.is_file() {
-        Ok(skel_path)
-    } else {
-        Err(Box::from(format!(
-            "skeleton file does not exist or is not a file {}",
-            skel_path.to_string_lossy().to_string()
-        )))
+    merge_main_configs(
+        &sources,
+        &HandlebarsEngine::new(),
+        &HashMap::new(),
+        &mut state,
+    )?;
+
+    for warning in state.warnings {
+        reporter.warn(&warning);
     }
+
+    Ok(state.checkouts)
 }
 
-fn make_skel_config<P: AsRef<Path>>(
-    skel_config_path: P,
-    handle: &Handlebars,
+// get the main config file from a given path and return it
+fn get_main_config(
+    main_config_path: &Path,
+    engine: &dyn Engine,
     template_data: &HashMap<String, String>,
-) -> Result<SkelConfig, Box<dyn Error>> {
-    let skel_config_buf = fs::read_to_string(skel_config_path)?;
+) -> Result<MainConfig, Box<dyn Error>> {
+    let config_string = fs::read_to_string(main_config_path)
+        .map_err(|e| SkelError::io(main_config_path, e))?;
+
+    let templated_config_string = engine.render(
+        &main_config_path.to_string_lossy(),
+        &config_string,
+        template_data,
+    )?;
+
+    let config: MainConfig =
+        toml::from_str(&templated_config_string).map_err(|e| {
+            SkelError::parse(main_config_path, &templated_config_string, &e)
+        })?;
+
+    Ok(config)
+}
+
+// the number of edits to turn `a` in to `b`, swapping two letters next to
+// each other is one edit
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    // the distances for the last two rows
+    let mut before: Vec<usize> = vec![0; b.len() + 1];
+    let mut last: Vec<usize> = (0..=b.len()).collect();
+
+    for i in 1..=a.len() {
+        let mut row = vec![i; b.len() + 1];
+
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+
+            row[j] = (last[j] + 1).min(row[j - 1] + 1).min(last[j - 1] + cost);
+
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(before[j - 2] + 1);
+            }
+        }
+
+        before = last;
+        last = row;
+    }
+
+    last[b.len()]
+}
+
+// the keys and aliases that are close to a name that was not found, closest
+// first
+fn suggestions(target: &str, main_config: &MainConfig) -> Vec<String> {
+    let target = target.to_lowercase();
+
+    // allow more edits for a longer name
+    let max_distance = (target.chars().count() / 3).max(1);
+
+    let mut close: Vec<(usize, &String)> = main_config
+        .skeletons
+        .iter()
+        .flat_map(|(key, skeleton)| {
+            std::iter::once(key).chain(skeleton.aliases.iter())
+        })
+        .map(|name| (edit_distance(&target, &name.to_lowercase()), name))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect();
+
+    close.sort();
+    close.dedup_by(|a, b| a.1 == b.1);
+
+    close.into_iter().map(|(_, name)| name.clone()).collect()
+}
+
+// the one skeleton with a key or alias that starts with `target`
+fn prefix_skeleton<'a>(
+    target: &str,
+    main_config: &'a MainConfig,
+) -> Result<Option<(&'a String, &'a Skeleton)>, Box<dyn Error>> {
+    let prefix = main_config.match_name(target);
+
+    let mut candidates: Vec<(&String, &Skeleton)> = main_config
+        .skeletons
+        .iter()
+        .filter(|(key, skeleton)| {
+            std::iter::once(*key)
+                .chain(skeleton.aliases.iter())
+                .any(|name| main_config.match_name(name).starts_with(&prefix))
+        })
+        .collect();
+
+    candidates.sort_by_key(|(key, _)| key.as_str());
+
+    match candidates.as_slice() {
+        [] => Ok(None),
+        [found] => Ok(Some(*found)),
+        // an ambiguous prefix did not find a skeleton, the candidates are
+        // suggested like a typo would be
+        _ => Err(Box::new(SkelError::SkeletonNotFound {
+            name: target.to_string(),
+            path: None,
+            suggestions: candidates
+                .iter()
+                .map(|(key, _)| key.to_string())
+                .collect(),
+        })),
+    }
+}
+
+// find a skeleton and its key by name or alias, a key always beats an alias
+// and an exact match always beats a prefix
+pub(crate) fn find_skeleton<'a>(
+    target: &str,
+    main_config: &'a MainConfig,
+) -> Result<(&'a String, &'a Skeleton), Box<dyn Error>> {
+    let skeleton =
+        if let Some(found) = main_config.skeletons.get_key_value(target) {
+            Some(found)
+        } else if let Some(found) = main_config
+            .skeletons
+            .iter()
+            .find(|(_, skeleton)| skeleton.aliases.iter().any(|s| s == target))
+        {
+            Some(found)
+        } else if main_config.ignore_case() {
+            let target = main_config.match_name(target);
+
+            let matches =
+                |name: &String| main_config.match_name(name) == target;
+
+            main_config
+                .skeletons
+                .iter()
+                .find(|(key, _)| matches(key))
+                .or_else(|| {
+                    main_config.skeletons.iter().find(|(_, skeleton)| {
+                        skeleton.aliases.iter().any(matches)
+                    })
+                })
+        } else {
+            None
+        };
+
+    let skeleton = match skeleton {
+        None if main_config.prefix_match.unwrap_or(false) => {
+            prefix_skeleton(target, main_config)?
+        }
+        skeleton => skeleton,
+    };
+
+    skeleton.ok_or_else(|| {
+        Box::from(SkelError::SkeletonNotFound {
+            name: target.to_string(),
+            path: None,
+            suggestions: suggestions(target, main_config),
+        })
+    })
+}
+
+// the path of a skeleton found in the main config, the file has to exist
+fn skeleton_file_path(
+    target: &str,
+    skeleton: &Skeleton,
+) -> Result<PathBuf, Box<dyn Error>> {
+    let skel_path = PathBuf::from(&skeleton.path);
+
+    if skel_path.is_file() {
+        Ok(skel_path)
+    } else {
+        Err(Box::new(SkelError::SkeletonNotFound {
+            name: target.to_string(),
+            path: Some(skel_path),
+            suggestions: vec![],
+        }))
+    }
+}
+
+// check the whole project name matches `name_pattern`
+fn check_name(
+    name: &str,
+    pattern: Option<&str>,
+    hint: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    let pattern = match pattern {
+        Some(pattern) => pattern,
+        None => return Ok(()),
+    };
+
+    let regex = Regex::new(&format!("^(?:{})$", pattern))
+        .map_err(|e| format!("name_pattern is not a valid regex {}", e))?;
+
+    if regex.is_match(name) {
+        return Ok(());
+    }
+
+    let mut msg = format!(
+        "project name {} does not match {}",
+        paint(Style::Value, Stream::Stderr, name),
+        pattern
+    );
+
+    if let Some(hint) = hint {
+        msg.push_str(&format!("\n  hint: {}", hint));
+    }
+
+    Err(Box::from(msg))
+}
+
+// template the derived variables with the variables so far and add them
+//
+// a derived variable can not use another derived variable, and a variable
+// given to the generator wins over a derived one
+fn add_derived(
+    derived: &BTreeMap<String, String>,
+    engine: &dyn Engine,
+    template_data: &mut HashMap<String, String>,
+) -> Result<(), Box<dyn Error>> {
+    let base_data = template_data.clone();
+
+    for (key, template) in derived.iter() {
+        if BUILT_IN_VARS.contains(&key.as_str()) {
+            return Err(Box::from(format!(
+                "derived.{} can not replace a built in variable",
+                key
+            )));
+        }
+
+        if base_data.contains_key(key) {
+            continue;
+        }
+
+        let value =
+            engine.render(&format!("derived.{}", key), template, &base_data)?;
+
+        template_data.insert(key.clone(), value);
+    }
+
+    Ok(())
+}
+
+/// a variable a skeleton declares in `derived`, `--var` gives it another value
+#[derive(Clone, Debug, PartialEq)]
+pub struct SkelVar {
+    pub key: String,
+    /// the template the value is derived from
+    pub template: String,
+    /// the derived value for the project name, `None` without a name
+    pub default: Option<String>,
+}
+
+// only the `derived` table of a skeleton
+#[derive(Deserialize)]
+struct DerivedOnly {
+    derived: Option<BTreeMap<String, String>>,
+}
+
+/// the variables a skeleton declares, each is templated with the project name
+/// for its default when a name is given. a skeleton that is not valid toml
+/// before templating can't have `derived` so it has none
+pub fn skeleton_vars(
+    skel_path: &Path,
+    name: Option<&str>,
+) -> Result<Vec<SkelVar>, SkelError> {
+    let text = fs::read_to_string(skel_path)
+        .map_err(|e| SkelError::io(skel_path, e))?;
+
+    let derived = toml::from_str::<DerivedOnly>(&text)
+        .ok()
+        .and_then(|derived_only| derived_only.derived)
+        .unwrap_or_default();
+
+    let mut defaults = HashMap::new();
+
+    if let Some(name) = name {
+        let engine = engine_from_source(&text)
+            .map_err(|e| format!("{} {}", skel_path.display(), e))?
+            .engine();
+
+        let skel_dir = skel_path.parent().unwrap_or_else(|| Path::new(""));
+
+        defaults.insert("name".to_string(), name.to_string());
+        defaults.insert(
+            "skel-dir".to_string(),
+            skel_dir.to_string_lossy().to_string(),
+        );
+
+        add_derived(&derived, engine.as_ref(), &mut defaults)?;
+    }
+
+    Ok(derived
+        .into_iter()
+        .map(|(key, template)| SkelVar {
+            default: defaults.get(&key).cloned(),
+            key,
+            template,
+        })
+        .collect())
+}
+
+fn make_skel_config<P: AsRef<Path>>(
+    skel_config_path: P,
+    template_data: &mut HashMap<String, String>,
+    check_names: bool,
+) -> Result<SkelConfig, Box<dyn Error>> {
+    let skel_config_path = skel_config_path.as_ref();
+
+    let skel_config_buf = fs::read_to_string(skel_config_path)
+        .map_err(|e| SkelError::io(skel_config_path, e))?;
+
+    parse_skel_config(
+        skel_config_path,
+        &skel_config_buf,
+        template_data,
+        check_names,
+    )
+}
+
+// template and parse the text of a skeleton, `skel_config_path` is where the
+// text came from
+//
+// the skeleton is templated with the engine it asks for, so `engine` is read
+// before templating. `check_names` is off to preview a skeleton with a name
+// that was not given by the user
+pub(crate) fn parse_skel_config(
+    skel_config_path: &Path,
+    skel_config_buf: &str,
+    template_data: &mut HashMap<String, String>,
+    check_names: bool,
+) -> Result<SkelConfig, Box<dyn Error>> {
+    // check the version first, a newer skeleton might not template
+    check_skel_version(skel_config_path, raw_meta(skel_config_buf).as_ref())?;
+
+    let engine_kind = engine_from_source(skel_config_buf)
+        .map_err(|e| format!("{} {}", skel_config_path.display(), e))?;
+
+    let engine = engine_kind.engine();
+
+    let name = template_data.get("name").cloned().unwrap_or_default();
+
+    let raw_config: Result<RawSkelConfig, _> = toml::from_str(skel_config_buf);
+
+    if let Ok(ref raw_config) = raw_config {
+        if check_names {
+            check_name(
+                &name,
+                raw_config.name_pattern.as_deref(),
+                raw_config.name_hint.as_deref(),
+            )?;
+        }
+
+        if let Some(ref derived) = raw_config.derived {
+            add_derived(derived, engine.as_ref(), template_data)?;
+        }
+    }
+
+    let templated_config_string = engine.render(
+        &skel_config_path.to_string_lossy(),
+        skel_config_buf,
+        template_data,
+    )?;
+
+    let mut skel_config: SkelConfig = toml::from_str(&templated_config_string)
+        .map_err(|e| {
+            SkelError::parse(skel_config_path, &templated_config_string, &e)
+        })?;
+
+    match raw_config {
+        Ok(raw_config) => {
+            if skel_config.steps.is_some() {
+                skel_config.steps = raw_config.steps;
+            }
+
+            skel_config.raw_build = raw_config.build;
+            skel_config.raw_derived = raw_config.derived;
+        }
+        // a skeleton with steps or derived variables needs to be valid toml
+        // before templating
+        Err(e)
+            if skel_config.steps.is_some() || skel_config.derived.is_some() =>
+        {
+            return Err(Box::new(SkelError::parse(
+                skel_config_path,
+                skel_config_buf,
+                &e,
+            )));
+        }
+        // the templated script is the best we can do
+        Err(_) => {
+            if check_names {
+                check_name(
+                    &name,
+                    skel_config.name_pattern.as_deref(),
+                    skel_config.name_hint.as_deref(),
+                )?;
+            }
+
+            skel_config.raw_build = skel_config.build.clone();
+        }
+    }
+
+    check_skel_version(skel_config_path, skel_config.meta.as_ref())?;
+
+    skel_config.engine = Some(engine_kind);
+
+    Ok(skel_config)
+}
+
+// the exec options given to the generator win over the ones in the skeleton,
+// `env_allow` is added to
+fn merge_exec(overrides: &SkelExec, mut exec: SkelExec) -> SkelExec {
+    if overrides.clear_env.is_some() {
+        exec.clear_env = overrides.clear_env;
+    }
+
+    if let Some(ref env_allow) = overrides.env_allow {
+        exec.env_allow
+            .get_or_insert_with(Vec::new)
+            .extend(env_allow.iter().cloned());
+    }
+
+    if overrides.timeout.is_some() {
+        exec.timeout = overrides.timeout;
+    }
+
+    if overrides.umask.is_some() {
+        exec.umask = overrides.umask.clone();
+    }
+
+    if overrides.limits.is_some() {
+        exec.limits = overrides.limits.clone();
+    }
+
+    if overrides.work_dir.is_some() {
+        exec.work_dir = overrides.work_dir.clone();
+    }
+
+    exec
+}
+
+/// resolve config
+///
+/// # Arguments
+///
+/// * `gen` - the generator options
+/// * `root_path` - the path to make skel in to
+/// * `name` - the name of the new project
+/// * `sources` - whether git sources are fetched or only read from the cache
+pub(crate) fn resolve_config(
+    gen: &Generator,
+    root_path: PathBuf,
+    name: String,
+    sources: SourceMode,
+) -> Result<RunConfig, SkelError> {
+    // a skeleton given as a file or as text does not need a main config
+    let given_skeleton = match (&gen.skeleton_text, &gen.skeleton_path) {
+        (Some(_), _) => Some(
+            env::current_dir()
+                .map_err(|e| SkelError::io(Path::new("."), e))?
+                .join(STDIN_SKELETON),
+        ),
+        (None, Some(path)) if path.is_file() => Some(path.clone()),
+        (None, Some(path)) => {
+            return Err(SkelError::SkeletonNotFound {
+                name: path.display().to_string(),
+                path: Some(path.clone()),
+                suggestions: vec![],
+            })
+        }
+        (None, None) => None,
+    };
+
+    let config_paths = match config_sources(gen.config_path.as_deref()) {
+        Ok(config_paths) => config_paths,
+        Err(_) if given_skeleton.is_some() => vec![],
+        Err(e) => return Err(e),
+    };
+
+    let reporter = Reporter::new(gen.verbosity, gen.log_file.clone());
+
+    // the built in variables win over the ones given to the generator
+    let mut template_data: HashMap<String, String> = gen.vars.clone();
+
+    template_data.insert("name".to_string(), name);
+
+    let root_var = match gen.root_var {
+        Some(ref root_var) => root_var.clone(),
+        None => root_path.to_string_lossy().to_string(),
+    };
+
+    template_data.insert("root".to_string(), root_var);
+
+    // `config-dir` is the dir of the main config the skeleton is in, for a
+    // skeleton file it is the highest priority main config or the skeleton
+    // dir when there is no main config
+    let (skel_config_path, config_path) = match given_skeleton {
+        Some(skeleton_path) => {
+            let config_path = config_paths
+                .last()
+                .map_or_else(|| skeleton_path.clone(), |s| s.path.clone());
+
+            (skeleton_path, config_path)
+        }
+        None => {
+            let mut state = ReadState {
+                sources,
+                ..Default::default()
+            };
+
+            let (main_config, _) = merge_main_configs(
+                &config_paths,
+                &HandlebarsEngine::new(),
+                &template_data,
+                &mut state,
+            )?;
+
+            for warning in state.warnings {
+                reporter.warn(&warning);
+            }
+
+            let target = gen
+                .skeleton
+                .as_deref()
+                .ok_or("did not get a skeleton to make some how")?;
+
+            let (_, skeleton) = find_skeleton(target, &main_config)?;
+
+            (
+                skeleton_file_path(target, skeleton)?,
+                skeleton.source.clone(),
+            )
+        }
+    };
+
+    let config_dir = config_path
+        .parent()
+        .ok_or("could not get the parent dir for the main config")?;
+
+    template_data.insert(
+        "config-dir".to_string(),
+        config_dir.to_string_lossy().to_string(),
+    );
+
+    template_data.insert(
+        "skel-dir".to_string(),
+        skel_config_path
+            .parent()
+            .unwrap()
+            .to_string_lossy()
+            .to_string(),
+    );
+
+    let skel_conf = match gen.skeleton_text {
+        Some(ref text) => parse_skel_config(
+            &skel_config_path,
+            text,
+            &mut template_data,
+            !gen.skip_name_check,
+        )?,
+        None => make_skel_config(
+            &skel_config_path,
+            &mut template_data,
+            !gen.skip_name_check,
+        )?,
+    };
+
+    let engine = skel_conf.engine.unwrap_or_default().engine();
+
+    let exec =
+        merge_exec(&gen.exec, skel_conf.exec.clone().unwrap_or_default());
+
+    reporter.debug(&format!("using skeleton {}", skel_config_path.display()));
+
+    let run_conf = RunConfig {
+        skel_conf,
+        skel_path: skel_config_path,
+        root_path,
+        template_data,
+        engine,
+        no_build: gen.no_build,
+        trust: gen.trust,
+        exec,
+        reporter,
+    };
+
+    Ok(run_conf)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::test_utils;
+
+    fn fake_main_config_duplicate() -> MainConfig {
+        let mut main_config = MainConfig::default();
+
+        let test_key_1 = test_utils::TEST_PROJECT_KEY.into();
+        let test_skeleton_1 = Skeleton {
+            path: test_utils::TEST_PROJECT_PATH.into(),
+            aliases: test_utils::TEST_PROJECT_ALIASES
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            ..Default::default()
+        };
+        main_config.skeletons.insert(test_key_1, test_skeleton_1);
+
+        let test_key_2 = "another_test_key".into();
+        let test_skeleton_2 = Skeleton {
+            path: test_utils::TEST_PROJECT_PATH.into(),
+            aliases: test_utils::TEST_PROJECT_ALIASES
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            ..Default::default()
+        };
+        main_config.skeletons.insert(test_key_2, test_skeleton_2);
+
+        main_config
+    }
+
+    fn fake_main_config(test_data: &test_utils::TestData) -> MainConfig {
+        let mut main_config = MainConfig::default();
+
+        let test_key_1 = test_utils::TEST_PROJECT_KEY.into();
+
+        let test_skeleton_1 = Skeleton {
+            path: test_utils::TEST_PROJECT_PATH
+                .replace("{{config-dir}}", &test_data.temp_path_string),
+            aliases: test_utils::TEST_PROJECT_ALIASES
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            ..Default::default()
+        };
+        main_config.skeletons.insert(test_key_1, test_skeleton_1);
+
+        let test_key_2 = "test_key_2".into();
+        let test_skeleton_2 = Skeleton {
+            path: "test_project_2.toml".into(),
+            aliases: vec!["a".into(), "A".into()],
+            ..Default::default()
+        };
+
+        main_config.skeletons.insert(test_key_2, test_skeleton_2);
+
+        main_config
+    }
+
+    #[test]
+    fn test_find_config_sources() {
+        let test_data = test_utils::TestData::default();
+        let temp = &test_data.temp_path;
+
+        let system = temp.join("system/skel/config.toml");
+        let user = temp.join("user/skel/config.toml");
+        let project = temp.join("repo/.skel/config.toml");
+        let env_config = temp.join("env.toml");
+
+        for path in [&system, &user, &project, &env_config] {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "[skeletons]").unwrap();
+        }
+
+        let nested = temp.join("repo/src/nested");
+
+        fs::create_dir_all(&nested).unwrap();
+
+        let vars: HashMap<&str, OsString> = HashMap::from([
+            ("XDG_CONFIG_DIRS", temp.join("system").into_os_string()),
+            ("XDG_CONFIG_HOME", temp.join("user").into_os_string()),
+            ("SKEL_CONFIG", env_config.clone().into_os_string()),
+        ]);
+
+        let var = |key: &str| vars.get(key).cloned();
+
+        let sources = find_config_sources(&var, &nested)
+            .expect("did not find the config sources");
+
+        assert_eq!(
+            sources,
+            vec![
+                ConfigSource {
+                    layer: ConfigLayer::System,
+                    path: system
+                },
+                ConfigSource {
+                    layer: ConfigLayer::User,
+                    path: user
+                },
+                ConfigSource {
+                    layer: ConfigLayer::Project,
+                    path: project
+                },
+                ConfigSource {
+                    layer: ConfigLayer::Env,
+                    path: env_config
+                },
+            ]
+        );
+
+        let missing_env = |key: &str| match key {
+            "SKEL_CONFIG" => Some(temp.join("missing.toml").into_os_string()),
+            _ => None,
+        };
+
+        assert!(
+            find_config_sources(&missing_env, temp).is_err(),
+            "did not fail on a missing SKEL_CONFIG"
+        );
+    }
+
+    #[test]
+    fn test_config_sources_alt() {
+        let mut test_data = test_utils::TestData::default();
+
+        test_data.make_configs();
+
+        let test_config_path = test_data.temp_path.join("test_config.toml");
+
+        fs::write(&test_config_path, test_utils::TEST_CONFIG)
+            .expect("could not make test config");
+
+        let sources = config_sources(Some(&test_config_path))
+            .expect("did not use the alt config");
+
+        assert_eq!(
+            sources,
+            vec![ConfigSource {
+                layer: ConfigLayer::Alt,
+                path: test_config_path
+            }]
+        );
+
+        assert!(
+            config_sources(Some(&test_data.temp_path.join("missing.toml")))
+                .is_err(),
+            "some how found a config?"
+        );
+    }
+
+    #[test]
+    fn test_merge_main_configs() {
+        let test_data = test_utils::TestData::default();
+
+        let lower = test_data.temp_path.join("lower/config.toml");
+        let higher = test_data.temp_path.join("higher/config.toml");
+
+        fs::create_dir_all(lower.parent().unwrap()).unwrap();
+        fs::create_dir_all(higher.parent().unwrap()).unwrap();
+
+        fs::write(
+            &lower,
+            "[skeletons.rs]\npath = \"{{config-dir}}/rs.toml\"\naliases = [\"r\"]\n\
+            [skeletons.js]\npath = \"js.toml\"\naliases = [\"j\"]\n",
+        )
+        .unwrap();
+
+        fs::write(
+            &higher,
+            "[skeletons.rs]\npath = \"{{config-dir}}/rs.toml\"\naliases = [\"r\"]\n",
+        )
+        .unwrap();
+
+        let sources = [&lower, &higher].map(|path| ConfigSource {
+            layer: ConfigLayer::User,
+            path: path.clone(),
+        });
+
+        let (merged, shadows) = merge_main_configs(
+            &sources,
+            &HandlebarsEngine::new(),
+            &HashMap::new(),
+            &mut ReadState::default(),
+        )
+        .expect("did not merge the main configs");
+
+        let rs = &merged.skeletons["rs"];
+
+        assert_eq!(rs.source, higher, "the higher layer did not win");
+        assert_eq!(
+            rs.path,
+            format!("{}/rs.toml", higher.parent().unwrap().display()),
+            "did not template each config with its own config-dir"
+        );
+
+        assert_eq!(merged.skeletons["js"].source, lower);
+
+        assert_eq!(shadows.len(), 1);
+        assert_eq!(shadows[0].shadowed, lower);
+
+        fs::write(
+            &higher,
+            "[skeletons.go]\npath = \"go.toml\"\naliases = [\"j\"]\n",
+        )
+        .unwrap();
+
+        assert!(
+            merge_main_configs(
+                &sources,
+                &HandlebarsEngine::new(),
+                &HashMap::new(),
+                &mut ReadState::default(),
+            )
+            .is_err(),
+            "did not check the merged config for duplicate aliases"
+        );
+    }
+
+    #[test]
+    fn test_read_config_tree() {
+        let test_data = test_utils::TestData::default();
+
+        let config = test_data.temp_path.join("config.toml");
+        let projects = test_data.temp_path.join("projects");
+
+        fs::create_dir_all(projects.join("web")).unwrap();
+
+        fs::write(
+            projects.join("cli.toml"),
+            "[meta]\naliases = [\"c\"]\ndescription = \"a cli\"\n",
+        )
+        .unwrap();
+        // a block helper is not valid toml before templating
+        fs::write(
+            projects.join("web/skeleton.toml"),
+            "{{#if web}}\ndirs = [\"static\"]\n{{/if}}\n",
+        )
+        .unwrap();
+        fs::write(projects.join("notes.txt"), "").unwrap();
+
+        fs::write(
+            test_data.temp_path.join("team.toml"),
+            "[skeletons.lib]\npath = \"lib.toml\"\naliases = [\"l\"]\n",
+        )
+        .unwrap();
+
+        fs::write(
+            &config,
+            "include = [\"team.toml\"]\n\
+            skeleton_dirs = [\"{{config-dir}}/projects\", \"missing\"]\n",
+        )
+        .unwrap();
+
+        let mut state = ReadState::default();
+
+        let skeletons = read_config_tree(
+            &config,
+            &HandlebarsEngine::new(),
+            &HashMap::new(),
+            &mut state,
+            &mut MainConfig::default(),
+        )
+        .expect("did not read the config tree");
+
+        let names: Vec<&str> =
+            skeletons.iter().map(|(name, _)| name.as_str()).collect();
+
+        assert_eq!(names, ["lib", "cli", "web"]);
+
+        assert_eq!(state.warnings.len(), 1);
+        assert!(
+            state.warnings[0].contains("web/skeleton.toml"),
+            "did not warn about the meta that can't be read"
+        );
+
+        let (_, lib) = &skeletons[0];
+
+        assert_eq!(
+            PathBuf::from(&lib.path),
+            test_data.temp_path.join("lib.toml"),
+            "did not make the path relative to the declaring config"
+        );
+        assert_eq!(lib.source, test_data.temp_path.join("team.toml"));
+
+        let (_, cli) = &skeletons[1];
+
+        assert_eq!(cli.aliases, ["c"], "did not read the aliases from meta");
+        assert_eq!(cli.description.as_deref(), Some("a cli"));
+        assert_eq!(cli.source, config);
+
+        assert_eq!(
+            PathBuf::from(&skeletons[2].1.path),
+            projects.join("web/skeleton.toml")
+        );
+
+        fs::write(
+            test_data.temp_path.join("team.toml"),
+            "include = [\"config.toml\"]\n",
+        )
+        .unwrap();
+
+        assert!(
+            read_config_tree(
+                &config,
+                &HandlebarsEngine::new(),
+                &HashMap::new(),
+                &mut ReadState::default(),
+                &mut MainConfig::default(),
+            )
+            .is_err(),
+            "some how included a config in itself"
+        );
+    }
+
+    #[test]
+    fn test_read_config_tree_git_source() {
+        let test_data = test_utils::TestData::default();
+        let temp = &test_data.temp_path;
+
+        test_utils::make_git_repo(temp);
+
+        let config = temp.join("config.toml");
+
+        // a relative repo is relative to the config
+        fs::write(
+            &config,
+            "[sources.team]\ngit = \"team.git\"\nrev = \"v1\"\n\
+            [skeletons.cli]\nsource = \"team\"\npath = \"rust/cli.toml\"\n",
+        )
+        .unwrap();
 
-    let templated_config_string = handle
-        .render_template(&skel_config_buf, template_data)
-        .expect("was not able to template skeleton");
+        // only the cache is read by default so nothing is fetched or locked
+        let mut cached = ReadState {
+            cache_dir: Some(temp.join("cache")),
+            ..Default::default()
+        };
 
-    toml::from_str(&templated_config_string).map_err(|e| {
-        Box::from(format!("skeleton config not formatted correctly {}", e))
-    })
-}
+        let skeletons = read_config_tree(
+            &config,
+            &HandlebarsEngine::new(),
+            &HashMap::new(),
+            &mut cached,
+            &mut MainConfig::default(),
+        )
+        .expect("did not read a config with a source that is not fetched");
 
-/// resolve config
-///
-/// # Arguments
-///
-/// * `args` - cli args
-/// * `root_path` - the path to make skel in to
-/// * `name` - the name of the new project
-pub fn resolve_config<'reg>(
-    args: &SkelArgs,
-    root_path: PathBuf,
-    name: String,
-) -> Result<RunConfig<'reg>, Box<dyn Error>> {
-    let main_config_path = get_main_config_path(args)?;
+        assert!(skeletons.is_empty(), "found a skeleton that is not fetched");
+        assert_eq!(cached.warnings.len(), 1, "did not warn about the source");
+        assert!(!temp.join("cache").exists(), "fetched while reading");
+        assert!(!temp.join("config.lock").exists(), "locked while reading");
 
-    let main_config_dir = main_config_path
-        .parent()
-        .ok_or("could not get the parent dir for the main config")?;
+        let mut state = ReadState {
+            cache_dir: Some(temp.join("cache")),
+            sources: SourceMode::Fetch,
+            ..Default::default()
+        };
 
-    let handle = instantiate_handlebars();
+        let skeletons = read_config_tree(
+            &config,
+            &HandlebarsEngine::new(),
+            &HashMap::new(),
+            &mut state,
+            &mut MainConfig::default(),
+        )
+        .expect("did not read a config with a git source");
 
-    let mut template_data: HashMap<String, String> = HashMap::new();
+        let (_, cli) = &skeletons[0];
 
-    template_data.insert("name".to_string(), name);
+        assert!(
+            cli.path.starts_with(&*temp.join("cache").to_string_lossy()),
+            "did not find the skeleton in the checkout {}",
+            cli.path
+        );
+        assert_eq!(fs::read_to_string(&cli.path).unwrap(), "dirs = [\"v1\"]\n");
 
-    template_data
-        .insert("root".to_string(), root_path.to_string_lossy().to_string());
+        assert!(
+            temp.join("config.lock").is_file(),
+            "did not lock the source"
+        );
+        assert_eq!(state.checkouts.len(), 1);
 
-    template_data.insert(
-        "config-dir".to_string(),
-        main_config_dir.to_string_lossy().to_string(),
-    );
+        fs::write(
+            &config,
+            "[skeletons.cli]\nsource = \"team\"\npath = \"rust/cli.toml\"\n",
+        )
+        .unwrap();
 
-    let skel_config_path = if let Some(ref skeleton_file) = args.skeleton_file {
-        PathBuf::from(skeleton_file)
-    } else {
-        let main_config =
-            get_main_config(&main_config_path, &handle, &template_data)?;
+        assert!(
+            read_config_tree(
+                &config,
+                &HandlebarsEngine::new(),
+                &HashMap::new(),
+                &mut ReadState::default(),
+                &mut MainConfig::default(),
+            )
+            .is_err(),
+            "some how found a source that is not declared"
+        );
+    }
 
-        get_skeleton_config_path(args, &main_config)?
-    };
+    #[test]
+    fn test_check_config() {
+        let test_data = test_utils::TestData::default();
 
-    template_data.insert(
-        "skel-dir".to_string(),
-        skel_config_path
-            .parent()
-            .unwrap()
-            .to_string_lossy()
-            .to_string(),
-    );
+        let main_config = fake_main_config(&test_data);
 
-    let skel_conf =
-        make_skel_config(&skel_config_path, &handle, &template_data)?;
+        assert!(
+            check_config(&main_config).is_ok(),
+            "did not find duplicate aliases"
+        );
+    }
 
-    let run_conf = RunConfig {
-        skel_conf,
-        root_path,
-        template_data,
-        handle,
-    };
+    #[test]
+    fn test_check_config_duplicates() {
+        let main_config = fake_main_config_duplicate();
 
-    Ok(run_conf)
-}
+        assert!(
+            check_config(&main_config).is_err(),
+            "did not find duplicate aliases"
+        );
+    }
 
-#[cfg(test)]
-mod test {
-    use super::*;
+    #[test]
+    fn test_check_config_namespace() {
+        let skeleton = |aliases: &[&str]| Skeleton {
+            path: "skeleton.toml".into(),
+            aliases: aliases.iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        };
 
-    use crate::test_utils;
+        let mut main_config = MainConfig::default();
 
-    fn fake_main_config_duplicate() -> MainConfig {
-        let mut main_config = MainConfig {
-            skeletons: HashMap::new(),
-        };
+        main_config.skeletons.insert("rs".into(), skeleton(&["r"]));
+        main_config.skeletons.insert("js".into(), skeleton(&["rs"]));
 
-        let test_key_1 = test_utils::TEST_PROJECT_KEY.into();
-        let test_skeleton_1 = Skeleton {
-            path: test_utils::TEST_PROJECT_PATH.into(),
-            aliases: test_utils::TEST_PROJECT_ALIASES
-                .iter()
-                .map(|s| s.to_string())
-                .collect(),
-        };
-        main_config.skeletons.insert(test_key_1, test_skeleton_1);
+        let err = check_config(&main_config)
+            .expect_err("did not find an alias that is another key");
 
-        let test_key_2 = "another_test_key".into();
-        let test_skeleton_2 = Skeleton {
-            path: test_utils::TEST_PROJECT_PATH.into(),
-            aliases: test_utils::TEST_PROJECT_ALIASES
-                .iter()
-                .map(|s| s.to_string())
-                .collect(),
-        };
-        main_config.skeletons.insert(test_key_2, test_skeleton_2);
+        assert!(
+            err.to_string().contains("js") && err.to_string().contains("rs"),
+            "did not name both skeletons {}",
+            err
+        );
 
         main_config
-    }
+            .skeletons
+            .insert("js".into(), skeleton(&["j", "j"]));
 
-    fn fake_main_config(test_data: &test_utils::TestData) -> MainConfig {
-        let mut main_config = MainConfig {
-            skeletons: HashMap::new(),
-        };
+        assert!(
+            check_config(&main_config).is_err(),
+            "did not find an alias repeated in one skeleton"
+        );
 
-        let test_key_1 = test_utils::TEST_PROJECT_KEY.into();
+        main_config
+            .skeletons
+            .insert("js".into(), skeleton(&["j", "R"]));
 
-        let test_skeleton_1 = Skeleton {
-            path: test_utils::TEST_PROJECT_PATH
-                .replace("{{config-dir}}", &test_data.temp_path_string),
-            aliases: test_utils::TEST_PROJECT_ALIASES
-                .iter()
-                .map(|s| s.to_string())
-                .collect(),
-        };
-        main_config.skeletons.insert(test_key_1, test_skeleton_1);
+        assert!(
+            check_config(&main_config).is_ok(),
+            "some how matched names that only differ in case"
+        );
 
-        let test_key_2 = "test_key_2".into();
-        let test_skeleton_2 = Skeleton {
-            path: "test_project_2.toml".into(),
-            aliases: vec!["a".into(), "A".into()],
-        };
+        main_config.ignore_case = Some(true);
 
-        main_config.skeletons.insert(test_key_2, test_skeleton_2);
+        assert!(
+            check_config(&main_config).is_err(),
+            "did not find names that only differ in case with ignore_case"
+        );
 
+        main_config.ignore_case = None;
         main_config
+            .skeletons
+            .insert("js".into(), skeleton(&["j", "list"]));
+
+        let err = check_config(&main_config)
+            .expect_err("did not find an alias that is a command");
+
+        assert!(err.to_string().contains("skel commands"));
     }
 
     #[test]
-    fn test_find_main_config_default() {
-        let mut test_data = test_utils::TestData::default();
-        test_data.make_configs();
+    fn test_find_skeleton_key_beats_alias() {
+        let mut main_config = MainConfig::default();
+
+        main_config.skeletons.insert(
+            "rs".into(),
+            Skeleton {
+                path: "rs.toml".into(),
+                ..Default::default()
+            },
+        );
+        main_config.skeletons.insert(
+            "rust".into(),
+            Skeleton {
+                path: "rust.toml".into(),
+                aliases: vec!["RS".into()],
+                ..Default::default()
+            },
+        );
 
-        let test_args = test_utils::test_args();
+        main_config.ignore_case = Some(true);
 
-        let mut test_config_path = test_data.temp_path;
+        let key = |target| find_skeleton(target, &main_config).unwrap().0;
 
-        test_config_path.push("skel/config.toml");
+        assert_eq!(key("rs"), "rs");
+        assert_eq!(key("RS"), "rust", "did not find the key of an alias");
+        assert_eq!(key("Rust"), "rust", "did not ignore case");
+        assert_eq!(
+            find_skeleton("Rust", &main_config).unwrap().1.path,
+            "rust.toml"
+        );
+    }
 
-        match get_main_config_path(&test_args) {
-            Ok(config_path) => {
-                assert_eq!(config_path, test_config_path);
-            }
-            Err(err) => panic!("{}", err),
-        }
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("python", "python"), 0);
+        assert_eq!(edit_distance("pyton", "python"), 1);
+        assert_eq!(edit_distance("rsut", "rust"), 1, "did not count a swap");
+        assert_eq!(edit_distance("", "js"), 2);
     }
 
     #[test]
-    fn test_find_main_config_args() {
-        let mut test_data = test_utils::TestData::default();
+    fn test_find_skeleton_suggestions_and_prefix() {
+        let skeleton = |aliases: &[&str]| Skeleton {
+            path: "skeleton.toml".into(),
+            aliases: aliases.iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        };
 
-        test_data.make_configs();
+        let mut main_config = MainConfig::default();
 
-        let mut test_args = test_utils::test_args();
-        let mut test_config_path = test_data.temp_path;
+        main_config
+            .skeletons
+            .insert("python".into(), skeleton(&["py"]));
+        main_config.skeletons.insert("pytest".into(), skeleton(&[]));
+        main_config
+            .skeletons
+            .insert("rust".into(), skeleton(&["rs"]));
 
-        test_config_path.push("test_config.toml");
+        let err =
+            SkelError::from(find_skeleton("pyton", &main_config).unwrap_err());
 
-        fs::write(&test_config_path, test_utils::TEST_CONFIG)
-            .expect("could not make test config");
+        match err {
+            SkelError::SkeletonNotFound {
+                ref suggestions, ..
+            } => assert_eq!(suggestions, &["python"]),
+            _ => panic!("did not make a skeleton not found error {}", err),
+        }
 
-        test_args.alt_config_path =
-            Some(test_config_path.as_os_str().to_str().unwrap().to_string());
+        assert!(
+            find_skeleton("ru", &main_config).is_err(),
+            "some how matched a prefix without prefix_match"
+        );
 
-        match get_main_config_path(&test_args) {
-            Ok(config_path) => {
-                assert_eq!(config_path, test_config_path);
-            }
-            Err(err) => panic!("{}", err),
-        }
-    }
+        main_config.prefix_match = Some(true);
 
-    #[test]
-    fn test_find_main_config_no_config() {
-        let test_args = test_utils::test_args();
+        assert!(
+            find_skeleton("ru", &main_config).is_ok(),
+            "did not find a skeleton from a unique prefix"
+        );
+
+        let err = find_skeleton("pyt", &main_config)
+            .expect_err("some how matched an ambiguous prefix");
 
         assert!(
-            get_main_config_path(&test_args).is_err(),
-            "some how found a config?"
+            err.to_string().contains("python")
+                && err.to_string().contains("pytest"),
+            "did not list every candidate {}",
+            err
+        );
+        assert!(
+            matches!(SkelError::from(err), SkelError::SkeletonNotFound { .. }),
+            "an ambiguous prefix is not a skeleton that was not found"
         );
     }
 
     #[test]
-    fn test_check_config() {
-        let test_data = test_utils::TestData::default();
+    fn test_skel_version() {
+        let version = |v: &str| parse_version(v).unwrap();
+
+        assert_eq!(version("1.2.3").numbers, [1, 2, 3]);
+        assert_eq!(version("0.2.0-beta.1+build").pre, ["beta", "1"]);
+        assert_eq!(parse_version("one"), None);
+        assert_eq!(parse_version("1.0-"), None);
+
+        let cmp = |a: &str, b: &str| compare_versions(&version(a), &version(b));
+
+        assert_eq!(cmp("0.1.1.0", "0.1.1"), Ordering::Equal);
+        assert_eq!(cmp("0.1", "0.1.1"), Ordering::Less);
+        assert_eq!(cmp("0.2.0-beta", "0.2.0"), Ordering::Less);
+        assert_eq!(cmp("0.2.0-beta.2", "0.2.0-beta.11"), Ordering::Less);
+        assert_eq!(cmp("0.2.0-alpha", "0.2.0-beta"), Ordering::Less);
+        assert_eq!(cmp("0.2.0-1", "0.2.0-alpha"), Ordering::Less);
+
+        let meta = |version: &str| SkelMeta {
+            min_skel_version: Some(version.into()),
+            ..Default::default()
+        };
 
-        let main_config = fake_main_config(&test_data);
+        let path = Path::new("skeleton.toml");
 
+        assert!(check_skel_version(path, None).is_ok());
+        assert!(check_skel_version(path, Some(&meta("0.1"))).is_ok());
         assert!(
-            check_config(&main_config).is_ok(),
-            "did not find duplicate aliases"
+            check_skel_version(
+                path,
+                Some(&meta(&format!("{}.0", env!("CARGO_PKG_VERSION"))))
+            )
+            .is_ok(),
+            "refused this version with a trailing 0"
+        );
+
+        let err = check_skel_version(path, Some(&meta("999.0.0")))
+            .expect_err("did not refuse a skeleton for a newer skel");
+
+        assert!(
+            err.to_string().contains("999.0.0"),
+            "did not name the version needed {}",
+            err
+        );
+
+        assert!(
+            check_skel_version(path, Some(&meta("new"))).is_err(),
+            "some how allowed a bad version"
+        );
+
+        let mut template_data = HashMap::new();
+
+        assert!(
+            parse_skel_config(
+                path,
+                "[meta]\nmin_skel_version = \"999.0\"\n",
+                &mut template_data,
+                true
+            )
+            .is_err(),
+            "did not check the version when parsing a skeleton"
         );
     }
 
     #[test]
-    fn test_check_config_duplicates() {
-        let main_config = fake_main_config_duplicate();
+    fn test_skeleton_meta() {
+        let test_data = test_utils::TestData::default();
 
-        assert!(
-            check_config(&main_config).is_err(),
-            "did not find duplicate aliases"
+        let skel_path = test_data.temp_path.join("skeleton.toml");
+
+        fs::write(
+            &skel_path,
+            "[meta]\ndescription = \"from the skeleton\"\n\
+            tags = [\"rust\", \"cli\"]\nversion = \"1.0.0\"\n\
+            authors = [\"someone\"]\n",
+        )
+        .unwrap();
+
+        let mut skeleton = Skeleton {
+            path: skel_path.to_string_lossy().to_string(),
+            tags: vec!["work".into(), "rust".into()],
+            ..Default::default()
+        };
+
+        let meta = skeleton.meta();
+
+        assert_eq!(meta.description.as_deref(), Some("from the skeleton"));
+        assert_eq!(meta.version.as_deref(), Some("1.0.0"));
+        assert_eq!(
+            meta.tags,
+            Some(vec!["work".into(), "rust".into(), "cli".into()]),
+            "did not merge the tags"
+        );
+
+        skeleton.description = Some("from the main config".into());
+
+        assert_eq!(
+            skeleton.meta().description.as_deref(),
+            Some("from the main config")
         );
+
+        // the file is only read the first time
+        fs::write(&skel_path, "[meta]\nversion = \"2.0.0\"\n").unwrap();
+
+        assert_eq!(skeleton.meta().version.as_deref(), Some("1.0.0"));
     }
 
     #[test]
@@ -516,11 +2325,11 @@ mod test {
                 test_data.temp_path_string.clone(),
             );
 
-            let handle = instantiate_handlebars();
-
-            if let Ok(main_config) =
-                get_main_config(main_config_path, &handle, &template_data)
-            {
+            if let Ok(main_config) = get_main_config(
+                main_config_path,
+                &HandlebarsEngine::new(),
+                &template_data,
+            ) {
                 let test_project =
                     main_config.skeletons.get("test_project").unwrap();
 
@@ -542,65 +2351,59 @@ mod test {
     }
 
     #[test]
-    fn test_skeleton_path_from_config_project_exists() {
+    fn test_find_skeleton_path_project_exists() {
         let test_data = test_utils::TestData::default();
         let main_config = fake_main_config(&test_data);
 
         let hand_made_project_path = test_utils::TEST_PROJECT_PATH
             .replace("{{config-dir}}", &test_data.temp_path_string);
 
-        let skel_path = skeleton_path_from_config(
-            test_utils::TEST_PROJECT_KEY,
-            &main_config,
-        )
-        .expect("did not find config");
+        let (key, skeleton) =
+            find_skeleton(test_utils::TEST_PROJECT_KEY, &main_config)
+                .expect("did not find config");
 
+        assert_eq!(key, test_utils::TEST_PROJECT_KEY);
         assert_eq!(
-            skel_path, hand_made_project_path,
+            skeleton.path, hand_made_project_path,
             "did not get the correct skeleton path"
         );
     }
 
     #[test]
-    fn test_skeleton_path_from_config_alias_exists() {
+    fn test_find_skeleton_path_alias_exists() {
         let test_data = test_utils::TestData::default();
         let main_config = fake_main_config(&test_data);
 
-        let target: String = "t".into();
-
         let hand_made_project_path = test_utils::TEST_PROJECT_PATH
             .replace("{{config-dir}}", &test_data.temp_path_string);
 
-        let skel_path = skeleton_path_from_config(&target, &main_config)
-            .expect("did not find config");
+        let (key, skeleton) =
+            find_skeleton("t", &main_config).expect("did not find config");
 
+        assert_eq!(key, test_utils::TEST_PROJECT_KEY);
         assert_eq!(
-            skel_path, hand_made_project_path,
+            skeleton.path, hand_made_project_path,
             "did not get the correct skeleton path"
         );
     }
 
     #[test]
-    fn test_skeleton_path_from_config_does_not_exist() {
+    fn test_find_skeleton_path_does_not_exist() {
         let test_data = test_utils::TestData::default();
         let main_config = fake_main_config(&test_data);
 
-        let target: String = "does_not_exist".into();
-
         assert!(
-            skeleton_path_from_config(&target, &main_config).is_err(),
+            find_skeleton("does_not_exist", &main_config).is_err(),
             "project  some how exists"
         );
     }
 
     #[test]
-    fn test_find_skeleton_config_path_from_aliases() {
+    fn test_skeleton_file_path_from_aliases() {
         let mut test_data = test_utils::TestData::default();
 
         test_data.make_configs();
 
-        let args = test_utils::test_args();
-
         let main_config = fake_main_config(&test_data);
 
         let mut hand_made_skel_path = test_data.temp_path.clone();
@@ -608,7 +2411,9 @@ mod test {
         hand_made_skel_path.push("projects");
         hand_made_skel_path.push(test_utils::TEST_SKEL_NAME);
 
-        match get_skeleton_config_path(&args, &main_config) {
+        let (_, skeleton) = find_skeleton("t", &main_config).unwrap();
+
+        match skeleton_file_path("t", skeleton) {
             Ok(config_dir) => assert_eq!(
                 config_dir, hand_made_skel_path,
                 "did not make skeleton path"
@@ -618,36 +2423,25 @@ mod test {
     }
 
     #[test]
-    fn test_find_skeleton_config_path_from_args() {
-        let mut test_data = test_utils::TestData::default();
-
-        test_data.make_configs();
-
-        let mut args = test_utils::test_args();
-
-        args.skeleton = None;
-
-        let mut skel_file = test_data.temp_path_string.clone();
-
-        // TODO: fix if windows support is added
-        skel_file.push_str("/test_skeleton_2.toml");
-
-        args.skeleton_file = Some(skel_file.clone());
-
-        let main_config = fake_main_config(&test_data);
-
-        let hand_made_skel_path = PathBuf::from(skel_file);
+    fn test_skeleton_file_path_missing() {
+        let test_data = test_utils::TestData::default();
 
-        fs::File::create(&hand_made_skel_path).unwrap();
+        let skeleton = Skeleton {
+            path: test_data
+                .temp_path
+                .join("not_there.toml")
+                .to_string_lossy()
+                .to_string(),
+            ..Default::default()
+        };
 
-        match get_skeleton_config_path(&args, &main_config) {
-            Ok(config_dir) => {
-                assert_eq!(
-                    config_dir, hand_made_skel_path,
-                    "did not make skeleton path"
-                )
-            }
-            Err(err) => panic!("did not find skeleton path {}", err),
+        match skeleton_file_path("not_there", &skeleton) {
+            Err(err) => assert!(
+                err.to_string().contains("not_there"),
+                "did not name the skeleton {}",
+                err
+            ),
+            Ok(path) => panic!("found a skeleton at {}", path.display()),
         }
     }
 
@@ -665,14 +2459,12 @@ mod test {
             test_data.temp_path_string.clone(),
         );
 
-        let handle = instantiate_handlebars();
-
-        let skel_config_path = handle
-            .render_template(test_utils::TEST_PROJECT_PATH, &template_data)
+        let skel_config_path = HandlebarsEngine::new()
+            .render("test", test_utils::TEST_PROJECT_PATH, &template_data)
             .unwrap();
 
         if let Err(err) =
-            make_skel_config(&skel_config_path, &handle, &template_data)
+            make_skel_config(&skel_config_path, &mut template_data, true)
         {
             panic!("{}", err);
         }
@@ -686,16 +2478,104 @@ mod test {
         template_data
             .insert("config-dir".to_string(), "test_config_dir".to_string());
 
-        let handle = instantiate_handlebars();
-
         if make_skel_config(
             "/tmp/does_not_exists.toml",
-            &handle,
-            &template_data,
+            &mut template_data,
+            true,
         )
         .is_ok()
         {
             panic!("some how config exists");
         }
     }
+
+    #[test]
+    fn test_check_name() {
+        let pattern = Some("[a-z][a-z0-9_]*");
+
+        assert!(check_name("test_project", pattern, None).is_ok());
+
+        assert!(
+            check_name("Test Project", pattern, None).is_err(),
+            "allowed a name that does not match"
+        );
+
+        let err = check_name("1test", pattern, Some("use snake case"))
+            .expect_err("allowed a name that only matches part of the pattern");
+
+        assert!(err.to_string().contains("hint: use snake case"));
+    }
+
+    #[test]
+    fn test_skel_config_derived() {
+        let test_data = test_utils::TestData::default();
+
+        let skel_path = test_data.temp_path.join("derived.toml");
+
+        fs::write(
+            &skel_path,
+            r#"
+name_pattern = "[a-z][a-z-]*"
+
+[derived]
+crate_name = "{{snake_case name}}"
+
+[[templates]]
+path = "lib.rs"
+template = "// {{crate_name}}"
+"#,
+        )
+        .expect("could not write the test skeleton");
+
+        let mut template_data = HashMap::new();
+        template_data.insert("name".to_string(), "my-project".to_string());
+
+        let skel_conf = make_skel_config(&skel_path, &mut template_data, true)
+            .expect("did not make the skeleton");
+
+        assert_eq!(template_data["crate_name"], "my_project");
+
+        let template = &skel_conf.templates.expect("did not get templates")[0];
+
+        assert_eq!(template.template.as_deref(), Some("// my_project"));
+
+        template_data.insert("name".to_string(), "My Project".to_string());
+
+        assert!(
+            make_skel_config(&skel_path, &mut template_data, true).is_err(),
+            "did not check the name"
+        );
+    }
+
+    #[test]
+    fn test_skeleton_vars() {
+        let test_data = test_utils::TestData::default();
+
+        let skel_path = test_data.temp_path.join("derived.toml");
+
+        fs::write(
+            &skel_path,
+            "[derived]\n\
+            crate_name = \"{{snake_case name}}\"\n\
+            author = \"someone\"\n",
+        )
+        .unwrap();
+
+        let vars = skeleton_vars(&skel_path, None).unwrap();
+
+        assert_eq!(
+            vars.iter().map(|var| var.key.as_str()).collect::<Vec<_>>(),
+            ["author", "crate_name"]
+        );
+        assert!(vars.iter().all(|var| var.default.is_none()));
+
+        let vars = skeleton_vars(&skel_path, Some("my-project")).unwrap();
+
+        assert_eq!(vars[1].template, "{{snake_case name}}");
+        assert_eq!(
+            vars[1].default.as_deref(),
+            Some("my_project"),
+            "did not template the default with the name"
+        );
+    }
 }
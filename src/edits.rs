@@ -0,0 +1,566 @@
+use std::{collections::HashMap, error::Error, fs};
+
+use regex::{Regex, RegexBuilder};
+
+use crate::{
+    config::{RunConfig, SkelEdit},
+    project_tree::{check_symlinks, normalize_path, project_path},
+    style::{paint, paint_diff_line, Stream, Style},
+    templating::Engine,
+};
+
+// a single edit with its regex compiled and its patch file read
+enum Edit<'a> {
+    Append(&'a str),
+    Prepend(&'a str),
+    InsertAfter(Regex, &'a str),
+    InsertBefore(Regex, &'a str),
+    Replace(Regex, &'a str),
+    Apply(String),
+}
+
+impl Edit<'_> {
+    fn name(&self) -> &'static str {
+        match self {
+            Edit::Append(_) => "append",
+            Edit::Prepend(_) => "prepend",
+            Edit::InsertAfter(..) => "insert_after",
+            Edit::InsertBefore(..) => "insert_before",
+            Edit::Replace(..) => "replace",
+            Edit::Apply(_) => "apply",
+        }
+    }
+}
+
+// a hunk from a unified diff
+struct Hunk {
+    old_start: usize,
+    old: Vec<String>,
+    new: Vec<String>,
+}
+
+fn edit_source(index: usize, edit: &SkelEdit) -> String {
+    format!("edits[{}] \"{}\"", index, edit.path)
+}
+
+// the regexes are multi line so `^` and `$` match at the start and end of a
+// line
+fn compile_regex(source: &str, regex: &str) -> Result<Regex, Box<dyn Error>> {
+    RegexBuilder::new(regex)
+        .multi_line(true)
+        .build()
+        .map_err(|e| Box::from(format!("{} has a bad regex {}", source, e)))
+}
+
+// make sure only one kind of edit is given and get it ready to use
+fn resolve_edit<'a>(
+    source: &str,
+    edit: &'a SkelEdit,
+    engine: &dyn Engine,
+    template_data: &HashMap<String, String>,
+) -> Result<Edit<'a>, Box<dyn Error>> {
+    let kinds = [
+        edit.append.is_some(),
+        edit.prepend.is_some(),
+        edit.insert_after.is_some(),
+        edit.insert_before.is_some(),
+        edit.replace.is_some(),
+        edit.apply.is_some(),
+    ];
+
+    if kinds.iter().filter(|k| **k).count() != 1 {
+        return Err(Box::from(format!(
+            "{} needs exactly one of append, prepend, insert_after, \
+            insert_before, replace or apply",
+            source
+        )));
+    }
+
+    let content = || {
+        edit.content
+            .as_deref()
+            .ok_or_else(|| format!("{} needs content", source))
+    };
+
+    let resolved = if let Some(ref append) = edit.append {
+        Edit::Append(append)
+    } else if let Some(ref prepend) = edit.prepend {
+        Edit::Prepend(prepend)
+    } else if let Some(ref regex) = edit.insert_after {
+        Edit::InsertAfter(compile_regex(source, regex)?, content()?)
+    } else if let Some(ref regex) = edit.insert_before {
+        Edit::InsertBefore(compile_regex(source, regex)?, content()?)
+    } else if let Some(ref regex) = edit.replace {
+        Edit::Replace(compile_regex(source, regex)?, content()?)
+    } else {
+        let patch_path = edit.apply.as_deref().unwrap_or_default();
+
+        let patch = fs::read_to_string(patch_path).map_err(|e| {
+            format!("{} could not read patch {} {}", source, patch_path, e)
+        })?;
+
+        Edit::Apply(engine.render(patch_path, &patch, template_data)?)
+    };
+
+    Ok(resolved)
+}
+
+fn with_newline(content: &str) -> String {
+    if content.ends_with('\n') {
+        content.to_string()
+    } else {
+        format!("{}\n", content)
+    }
+}
+
+// the start and line count of one side of a hunk header, `2,3` or `2` for a
+// single line
+fn parse_range(range: &str) -> Option<(usize, usize)> {
+    let mut parts = range.splitn(2, ',');
+
+    let start = parts.next()?.parse().ok()?;
+    let count = match parts.next() {
+        Some(count) => count.parse().ok()?,
+        None => 1,
+    };
+
+    Some((start, count))
+}
+
+// parse the hunks out of a unified diff, the file headers are ignored as the
+// edit already names the file
+//
+// each hunk takes exactly the number of lines its header counts so a removed
+// line that starts with `--` is not mistaken for a file header
+fn parse_patch(patch: &str) -> Result<Vec<Hunk>, Box<dyn Error>> {
+    let mut hunks: Vec<Hunk> = vec![];
+
+    // the old and new lines still to read for the last hunk
+    let mut old_left = 0;
+    let mut new_left = 0;
+
+    for line in patch.lines() {
+        if old_left == 0 && new_left == 0 {
+            // anything between hunks is a header
+            let header = match line.strip_prefix("@@ ") {
+                Some(header) => header,
+                None => continue,
+            };
+
+            let bad_header = || format!("bad hunk header {}", line);
+
+            let mut ranges = header.split_whitespace();
+
+            let (old_start, old_count) = ranges
+                .next()
+                .and_then(|range| range.strip_prefix('-'))
+                .and_then(parse_range)
+                .ok_or_else(bad_header)?;
+
+            let (_, new_count) = ranges
+                .next()
+                .and_then(|range| range.strip_prefix('+'))
+                .and_then(parse_range)
+                .ok_or_else(bad_header)?;
+
+            old_left = old_count;
+            new_left = new_count;
+
+            hunks.push(Hunk {
+                old_start,
+                old: vec![],
+                new: vec![],
+            });
+
+            continue;
+        }
+
+        let hunk = hunks.last_mut().expect("lines left without a hunk");
+
+        // `\ No newline at end of file` is not a line of either side
+        if line.starts_with('\\') {
+            continue;
+        }
+
+        // some editors strip the space from empty context lines
+        let (kind, content) = match line.chars().next() {
+            Some(kind) => (kind, &line[kind.len_utf8()..]),
+            None => (' ', ""),
+        };
+
+        match kind {
+            ' ' if old_left > 0 && new_left > 0 => {
+                hunk.old.push(content.to_string());
+                hunk.new.push(content.to_string());
+
+                old_left -= 1;
+                new_left -= 1;
+            }
+            '-' if old_left > 0 => {
+                hunk.old.push(content.to_string());
+
+                old_left -= 1;
+            }
+            '+' if new_left > 0 => {
+                hunk.new.push(content.to_string());
+
+                new_left -= 1;
+            }
+            _ => {
+                return Err(Box::from(format!(
+                    "hunk {} does not match the line counts in its header \
+                    at {}",
+                    hunks.len(),
+                    line
+                )))
+            }
+        }
+    }
+
+    if old_left > 0 || new_left > 0 {
+        Err(Box::from(format!(
+            "hunk {} ends before the line counts in its header",
+            hunks.len()
+        )))
+    } else if hunks.is_empty() {
+        Err(Box::from("patch has no hunks"))
+    } else {
+        Ok(hunks)
+    }
+}
+
+// apply the hunks in order, a hunk is tried where the header says first and
+// then anywhere after the previous hunk
+fn apply_patch(
+    text: &str,
+    patch: &str,
+) -> Result<Option<String>, Box<dyn Error>> {
+    let hunks = parse_patch(patch)?;
+
+    let lines: Vec<&str> = text.lines().collect();
+
+    let mut patched: Vec<String> = vec![];
+    let mut cursor = 0;
+
+    for hunk in hunks.iter() {
+        let matches_at = |pos: usize| {
+            pos + hunk.old.len() <= lines.len()
+                && lines[pos..pos + hunk.old.len()]
+                    .iter()
+                    .zip(hunk.old.iter())
+                    .all(|(a, b)| a == b)
+        };
+
+        let expected = hunk.old_start.saturating_sub(1).max(cursor);
+
+        let pos = if matches_at(expected) {
+            expected
+        } else {
+            match (cursor..=lines.len()).find(|pos| matches_at(*pos)) {
+                Some(pos) => pos,
+                None => return Ok(None),
+            }
+        };
+
+        patched.extend(lines[cursor..pos].iter().map(|l| l.to_string()));
+        patched.extend(hunk.new.iter().cloned());
+
+        cursor = pos + hunk.old.len();
+    }
+
+    patched.extend(lines[cursor..].iter().map(|l| l.to_string()));
+
+    let mut patched = patched.join("\n");
+
+    if !patched.is_empty() && (text.ends_with('\n') || text.is_empty()) {
+        patched.push('\n');
+    }
+
+    Ok(Some(patched))
+}
+
+// apply an edit to the text of a file, `None` means the anchor was not found
+fn apply_edit(
+    text: &str,
+    edit: &Edit,
+) -> Result<Option<String>, Box<dyn Error>> {
+    let edited = match edit {
+        Edit::Append(content) => {
+            let mut edited = text.to_string();
+
+            if !edited.is_empty() && !edited.ends_with('\n') {
+                edited.push('\n');
+            }
+
+            edited.push_str(content);
+
+            edited
+        }
+        Edit::Prepend(content) => format!("{}{}", with_newline(content), text),
+        Edit::InsertAfter(regex, content) => {
+            let found = match regex.find(text) {
+                Some(found) => found,
+                None => return Ok(None),
+            };
+
+            // the end of the line the match ends on
+            let line_end = match text[found.end()..].find('\n') {
+                Some(i) => found.end() + i + 1,
+                None => text.len(),
+            };
+
+            let mut edited = text[..line_end].to_string();
+
+            if !edited.ends_with('\n') {
+                edited.push('\n');
+            }
+
+            edited.push_str(&with_newline(content));
+            edited.push_str(&text[line_end..]);
+
+            edited
+        }
+        Edit::InsertBefore(regex, content) => {
+            let found = match regex.find(text) {
+                Some(found) => found,
+                None => return Ok(None),
+            };
+
+            // the start of the line the match starts on
+            let line_start = text[..found.start()]
+                .rfind('\n')
+                .map(|i| i + 1)
+                .unwrap_or(0);
+
+            format!(
+                "{}{}{}",
+                &text[..line_start],
+                with_newline(content),
+                &text[line_start..]
+            )
+        }
+        Edit::Replace(regex, content) => {
+            if !regex.is_match(text) {
+                return Ok(None);
+            }
+
+            regex.replace_all(text, *content).into_owned()
+        }
+        Edit::Apply(patch) => return apply_patch(text, patch),
+    };
+
+    Ok(Some(edited))
+}
+
+/// apply the `edits` list to files already in the project
+///
+/// an anchor that is not found or a file that does not exist is an error
+/// unless the edit is `optional`
+pub(crate) fn run_edits(run_conf: &RunConfig) -> Result<(), Box<dyn Error>> {
+    let edits = match run_conf.skel_conf.edits {
+        Some(ref edits) => edits,
+        None => return Ok(()),
+    };
+
+    let root = normalize_path(&run_conf.root_path);
+
+    for (i, edit) in edits.iter().enumerate() {
+        let source = edit_source(i, edit);
+        let optional = edit.optional.unwrap_or(false);
+
+        let resolved = resolve_edit(
+            &source,
+            edit,
+            run_conf.engine.as_ref(),
+            &run_conf.template_data,
+        )?;
+
+        let path = project_path(&root, &run_conf.template_data, &edit.path)?;
+
+        check_symlinks(&root, &path)?;
+
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(_) if optional => continue,
+            Err(e) => {
+                return Err(Box::from(format!(
+                    "{} could not read {} {}",
+                    source,
+                    path.display(),
+                    e
+                )))
+            }
+        };
+
+        match apply_edit(&text, &resolved)? {
+            Some(edited) => {
+                fs::write(&path, edited)?;
+
+                run_conf.reporter.created("edited", &path);
+            }
+            None if optional => continue,
+            None => {
+                return Err(Box::from(format!(
+                    "{} did not find where to {} in {}",
+                    source,
+                    resolved.name(),
+                    path.display()
+                )))
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// print the `edits` list in the order it would be applied
+pub(crate) fn print_edits(run_conf: &RunConfig) -> Result<(), Box<dyn Error>> {
+    let edits = match run_conf.skel_conf.edits {
+        Some(ref edits) => edits,
+        None => return Ok(()),
+    };
+
+    let root = normalize_path(&run_conf.root_path);
+
+    run_conf.reporter.info("  edits:");
+
+    for (i, edit) in edits.iter().enumerate() {
+        let source = edit_source(i, edit);
+
+        let resolved = resolve_edit(
+            &source,
+            edit,
+            run_conf.engine.as_ref(),
+            &run_conf.template_data,
+        )?;
+
+        let path = project_path(&root, &run_conf.template_data, &edit.path)?;
+
+        let (anchor, content) = match resolved {
+            Edit::Append(content) | Edit::Prepend(content) => (None, content),
+            Edit::InsertAfter(ref regex, content)
+            | Edit::InsertBefore(ref regex, content)
+            | Edit::Replace(ref regex, content) => {
+                (Some(regex.as_str()), content)
+            }
+            Edit::Apply(ref patch) => (None, patch.as_str()),
+        };
+
+        let is_patch = matches!(resolved, Edit::Apply(_));
+
+        run_conf.reporter.info(&format!(
+            "  {:>3}. {} -> {}",
+            i,
+            paint(Style::Kind, Stream::Stdout, resolved.name()),
+            path.display()
+        ));
+
+        if let Some(anchor) = anchor {
+            run_conf.reporter.info(&format!("    /{}/", anchor));
+        }
+
+        for line in content.lines() {
+            let line = if is_patch {
+                paint_diff_line(Stream::Stdout, line)
+            } else {
+                line.to_string()
+            };
+
+            run_conf.reporter.info(&format!("    {}", line));
+        }
+    }
+
+    run_conf.reporter.info("  ------");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TEST_TEXT: &str = "[package]\nname = \"test\"\n\n[dependencies]\n";
+
+    #[test]
+    fn test_apply_edit_insert() {
+        let regex = compile_regex("test", r"^\[dependencies\]").unwrap();
+
+        let after = Edit::InsertAfter(regex.clone(), "serde = \"1\"");
+
+        assert_eq!(
+            apply_edit(TEST_TEXT, &after).unwrap().unwrap(),
+            "[package]\nname = \"test\"\n\n[dependencies]\nserde = \"1\"\n"
+        );
+
+        let before = Edit::InsertBefore(regex, "[features]\n");
+
+        assert_eq!(
+            apply_edit(TEST_TEXT, &before).unwrap().unwrap(),
+            "[package]\nname = \"test\"\n\n[features]\n[dependencies]\n"
+        );
+    }
+
+    #[test]
+    fn test_apply_edit_replace() {
+        let regex = compile_regex("test", r#"name = "(\w+)""#).unwrap();
+
+        let replace = Edit::Replace(regex, r#"name = "$1-cli""#);
+
+        assert_eq!(
+            apply_edit(TEST_TEXT, &replace).unwrap().unwrap(),
+            "[package]\nname = \"test-cli\"\n\n[dependencies]\n"
+        );
+
+        let missing =
+            Edit::Replace(compile_regex("test", "version").unwrap(), "");
+
+        assert!(
+            apply_edit(TEST_TEXT, &missing).unwrap().is_none(),
+            "some how found an anchor that is not there"
+        );
+    }
+
+    #[test]
+    fn test_apply_edit_patch() {
+        let patch = "--- a/Cargo.toml\n+++ b/Cargo.toml\n\
+            @@ -2,3 +2,4 @@\n \
+            name = \"test\"\n+edition = \"2021\"\n \n \
+            [dependencies]\n";
+
+        let apply = Edit::Apply(patch.to_string());
+
+        assert_eq!(
+            apply_edit(TEST_TEXT, &apply).unwrap().unwrap(),
+            "[package]\nname = \"test\"\nedition = \"2021\"\n\n[dependencies]\n"
+        );
+
+        let bad_patch = "@@ -1,1 +1,1 @@\n-[workspace]\n+[package]\n";
+
+        assert!(
+            apply_edit(TEST_TEXT, &Edit::Apply(bad_patch.to_string()))
+                .unwrap()
+                .is_none(),
+            "applied a patch that does not match"
+        );
+
+        // a removed sql comment looks like a file header
+        let sql = "select 1;\n-- old comment\nselect 2;\n";
+        let sql_patch = "--- a/q.sql\n+++ b/q.sql\n\
+            @@ -1,3 +1,3 @@\n \
+            select 1;\n--- old comment\n+++ new comment\n select 2;\n";
+
+        assert_eq!(
+            apply_edit(sql, &Edit::Apply(sql_patch.to_string()))
+                .unwrap()
+                .unwrap(),
+            "select 1;\n++ new comment\nselect 2;\n"
+        );
+
+        let short_patch = "@@ -1,3 +1,3 @@\n select 1;\n";
+
+        assert!(
+            parse_patch(short_patch).is_err(),
+            "some how parsed a hunk shorter than its header"
+        );
+    }
+}
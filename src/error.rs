@@ -0,0 +1,297 @@
+use std::{
+    error::Error,
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+use handlebars::RenderError;
+
+/// everything that can go wrong making a project
+///
+/// each kind of error has its own exit code so scripts calling skel can tell
+/// them apart, see `exit_code`
+#[derive(Debug)]
+pub enum SkelError {
+    /// the main config does not exist
+    ConfigNotFound { path: PathBuf },
+    /// no skeleton or alias matched, or the skeleton file does not exist
+    ///
+    /// `suggestions` are the names and aliases close to `name`
+    SkeletonNotFound {
+        name: String,
+        path: Option<PathBuf>,
+        suggestions: Vec<String>,
+    },
+    /// a config file is not valid toml
+    Parse {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        snippet: String,
+        message: String,
+    },
+    /// a template could not be rendered
+    Render {
+        template: String,
+        variable: Option<String>,
+        message: String,
+    },
+    /// reading or writing a path failed
+    Io { path: PathBuf, source: io::Error },
+    /// the build script or a run step failed
+    BuildFailed {
+        name: String,
+        code: Option<i32>,
+        message: String,
+    },
+    /// anything else like bad args or conflicting entries
+    Other(String),
+}
+
+// the 1 based line and column of a byte offset and the text of that line
+fn line_col(text: &str, offset: usize) -> (usize, usize, &str) {
+    let offset = offset.min(text.len());
+
+    let line_start = text[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = text[offset..].find('\n').map_or(text.len(), |i| offset + i);
+
+    let line = text[..offset].matches('\n').count() + 1;
+    let column = text[line_start..offset].chars().count() + 1;
+
+    (line, column, &text[line_start..line_end])
+}
+
+// pull the variable name out of a handlebars error if there is one
+fn render_variable(desc: &str) -> Option<String> {
+    if let Some(rest) = desc.strip_prefix("Variable \"") {
+        rest.split('"').next().map(String::from)
+    } else {
+        desc.strip_prefix("did not find env var called ")
+            .map(String::from)
+    }
+}
+
+impl SkelError {
+    /// a toml error pointing at the line and column in `text`, the text that
+    /// was parsed from `path`
+    pub fn parse(path: &Path, text: &str, err: &toml::de::Error) -> Self {
+        let (line, column, line_text) =
+            line_col(text, err.span().map_or(0, |span| span.start));
+
+        let gutter = line.to_string().len();
+
+        let snippet = format!(
+            "{:gutter$} |\n{} | {}\n{:gutter$} | {:>column$}",
+            "",
+            line,
+            line_text,
+            "",
+            "^",
+            gutter = gutter,
+            column = column
+        );
+
+        SkelError::Parse {
+            path: path.to_path_buf(),
+            line,
+            column,
+            snippet,
+            message: err.message().trim().to_string(),
+        }
+    }
+
+    /// a handlebars error for the template at `template`
+    pub fn render(template: &str, err: &RenderError) -> Self {
+        SkelError::Render {
+            template: template.to_string(),
+            variable: render_variable(&err.desc),
+            message: err.to_string(),
+        }
+    }
+
+    pub fn io(path: &Path, source: io::Error) -> Self {
+        SkelError::Io {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    /// the exit code for the binary, 2 is left for bad args from clap
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            SkelError::Other(_) => 1,
+            SkelError::ConfigNotFound { .. } => 3,
+            SkelError::SkeletonNotFound { .. } => 4,
+            SkelError::Parse { .. } => 5,
+            SkelError::Render { .. } => 6,
+            SkelError::Io { .. } => 7,
+            SkelError::BuildFailed { .. } => 8,
+        }
+    }
+}
+
+impl fmt::Display for SkelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkelError::ConfigNotFound { path } => write!(
+                f,
+                "main config does not exist or is not a file {}",
+                path.display()
+            ),
+            SkelError::SkeletonNotFound {
+                path: Some(path), ..
+            } => write!(
+                f,
+                "skeleton file does not exist or is not a file {}",
+                path.display()
+            ),
+            SkelError::SkeletonNotFound {
+                name,
+                path: None,
+                suggestions,
+            } => {
+                write!(
+                    f,
+                    "did not find matching skeleton or alias for {}",
+                    name
+                )?;
+
+                if !suggestions.is_empty() {
+                    write!(f, "\n  did you mean: {}", suggestions.join(", "))?;
+                }
+
+                Ok(())
+            }
+            SkelError::Parse {
+                path,
+                line,
+                column,
+                snippet,
+                message,
+            } => write!(
+                f,
+                "could not parse {}:{}:{} {}\n{}",
+                path.display(),
+                line,
+                column,
+                message,
+                snippet
+            ),
+            SkelError::Render {
+                template,
+                variable,
+                message,
+            } => {
+                write!(f, "could not render {}", template)?;
+
+                if let Some(variable) = variable {
+                    write!(f, " variable {}", variable)?;
+                }
+
+                write!(f, ", {}", message)
+            }
+            SkelError::Io { path, source } => {
+                write!(f, "{} {}", path.display(), source)
+            }
+            SkelError::BuildFailed { name, message, .. } => {
+                write!(f, "{} failed with {}", name, message)
+            }
+            SkelError::Other(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl Error for SkelError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SkelError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+// the internals return `Box<dyn Error>`, keep a `SkelError` if that is what
+// is in the box
+impl From<Box<dyn Error>> for SkelError {
+    fn from(err: Box<dyn Error>) -> Self {
+        match err.downcast::<SkelError>() {
+            Ok(skel_err) => *skel_err,
+            Err(err) => SkelError::Other(err.to_string()),
+        }
+    }
+}
+
+impl From<String> for SkelError {
+    fn from(msg: String) -> Self {
+        SkelError::Other(msg)
+    }
+}
+
+impl From<&str> for SkelError {
+    fn from(msg: &str) -> Self {
+        SkelError::Other(msg.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_error() {
+        let text = "dirs = [\"src\"]\nfiles = [\"a\" \"b\"]\n";
+
+        let toml_err = toml::from_str::<toml::Value>(text).unwrap_err();
+
+        let err = SkelError::parse(Path::new("test.toml"), text, &toml_err);
+
+        match err {
+            SkelError::Parse {
+                line,
+                column,
+                ref snippet,
+                ..
+            } => {
+                assert_eq!((line, column), (2, 14));
+                assert_eq!(
+                    snippet,
+                    "  |\n2 | files = [\"a\" \"b\"]\n  |              ^"
+                );
+            }
+            _ => panic!("did not make a parse error {}", err),
+        }
+
+        assert_eq!(err.exit_code(), 5);
+    }
+
+    #[test]
+    fn test_from_box() {
+        let boxed: Box<dyn Error> = Box::new(SkelError::ConfigNotFound {
+            path: PathBuf::from("config.toml"),
+        });
+
+        assert!(
+            matches!(SkelError::from(boxed), SkelError::ConfigNotFound { .. }),
+            "lost the error kind in a box"
+        );
+
+        let boxed: Box<dyn Error> = Box::from("some string");
+
+        assert!(matches!(SkelError::from(boxed), SkelError::Other(_)));
+    }
+
+    #[test]
+    fn test_render_variable() {
+        assert_eq!(
+            render_variable("Variable \"name\" not found in strict mode."),
+            Some("name".into())
+        );
+
+        assert_eq!(
+            render_variable("did not find env var called HOME"),
+            Some("HOME".into())
+        );
+
+        assert_eq!(render_variable("missing helper"), None);
+    }
+}
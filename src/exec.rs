@@ -0,0 +1,427 @@
+use std::{
+    env,
+    error::Error,
+    io::{self, BufRead, BufReader, Read},
+    os::unix::process::{CommandExt, ExitStatusExt},
+    process::{Command, ExitStatus, Stdio},
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::{config::SkelExec, error::SkelError, report::Reporter};
+
+// how often to check if a script with a timeout is done
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+// the resource limits to set on a script, `None` means leave it alone
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+struct Limits {
+    cpu_seconds: Option<u64>,
+    memory_bytes: Option<u64>,
+    open_files: Option<u64>,
+    file_size_bytes: Option<u64>,
+}
+
+const GIB: u64 = 1024 * 1024 * 1024;
+
+// the named resource limit profiles
+fn limits_profile(profile: &str) -> Result<Limits, Box<dyn Error>> {
+    match profile {
+        "none" => Ok(Limits::default()),
+        "standard" => Ok(Limits {
+            cpu_seconds: Some(600),
+            memory_bytes: Some(8 * GIB),
+            open_files: Some(4096),
+            file_size_bytes: Some(4 * GIB),
+        }),
+        "strict" => Ok(Limits {
+            cpu_seconds: Some(120),
+            memory_bytes: Some(2 * GIB),
+            open_files: Some(256),
+            file_size_bytes: Some(GIB),
+        }),
+        _ => Err(Box::from(format!(
+            "unknown limits profile {}, use none, standard or strict",
+            profile
+        ))),
+    }
+}
+
+fn parse_umask(umask: &str) -> Result<u32, Box<dyn Error>> {
+    match u32::from_str_radix(umask, 8) {
+        Ok(mask) if mask <= 0o777 => Ok(mask),
+        _ => Err(Box::from(format!(
+            "umask should be an octal number like 022 not {}",
+            umask
+        ))),
+    }
+}
+
+/// check the exec options before anything is made so a bad option does not
+/// fail half way through
+pub(crate) fn check_exec(exec: &SkelExec) -> Result<(), Box<dyn Error>> {
+    if let Some(ref umask) = exec.umask {
+        parse_umask(umask)?;
+    }
+
+    if let Some(ref profile) = exec.limits {
+        limits_profile(profile)?;
+    }
+
+    Ok(())
+}
+
+// set the limits in the child before bash is run, this has to stick to async
+// signal safe calls
+fn set_limits(limits: Limits) -> io::Result<()> {
+    macro_rules! set_limit {
+        ($resource:expr, $value:expr) => {
+            if let Some(value) = $value {
+                let rlim = libc::rlimit {
+                    rlim_cur: value as libc::rlim_t,
+                    rlim_max: value as libc::rlim_t,
+                };
+
+                if unsafe { libc::setrlimit($resource, &rlim) } != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+        };
+    }
+
+    set_limit!(libc::RLIMIT_CPU, limits.cpu_seconds);
+    set_limit!(libc::RLIMIT_AS, limits.memory_bytes);
+    set_limit!(libc::RLIMIT_NOFILE, limits.open_files);
+    set_limit!(libc::RLIMIT_FSIZE, limits.file_size_bytes);
+
+    Ok(())
+}
+
+fn make_command(
+    script: &str,
+    exec: &SkelExec,
+) -> Result<Command, Box<dyn Error>> {
+    let mut build_script = String::from("#!/usr/bin/bash env\n\n");
+
+    build_script.push_str(script);
+
+    let mut command = Command::new("bash");
+
+    command
+        .arg("-c")
+        .arg(build_script)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        // put the script in its own process group so a timeout can kill
+        // everything it started
+        .process_group(0);
+
+    if let Some(ref work_dir) = exec.work_dir {
+        command.current_dir(work_dir);
+    }
+
+    if exec.clear_env.unwrap_or(false) {
+        command.env_clear();
+
+        for var in exec.env_allow.iter().flatten() {
+            if let Some(value) = env::var_os(var) {
+                command.env(var, value);
+            }
+        }
+    }
+
+    let umask = exec.umask.as_deref().map(parse_umask).transpose()?;
+
+    let limits = match exec.limits {
+        Some(ref profile) => limits_profile(profile)?,
+        None => Limits::default(),
+    };
+
+    if umask.is_some() || limits != Limits::default() {
+        unsafe {
+            command.pre_exec(move || {
+                if let Some(mask) = umask {
+                    libc::umask(mask as libc::mode_t);
+                }
+
+                set_limits(limits)
+            });
+        }
+    }
+
+    Ok(command)
+}
+
+// read a stream line by line, keeping when each line was read for the log
+fn read_lines<R: Read + Send + 'static>(
+    reader: Option<R>,
+    stderr: bool,
+) -> thread::JoinHandle<(Vec<u8>, Vec<OutputLine>)> {
+    thread::spawn(move || {
+        let mut buf = vec![];
+        let mut lines = vec![];
+
+        if let Some(reader) = reader {
+            let mut reader = BufReader::new(reader);
+
+            loop {
+                let mut line = vec![];
+
+                match reader.read_until(b'\n', &mut line) {
+                    Ok(0) | Err(_) => break,
+                    Ok(_) => {
+                        lines.push(OutputLine {
+                            time: SystemTime::now(),
+                            stderr,
+                            text: String::from_utf8_lossy(&line)
+                                .trim_end_matches('\n')
+                                .to_string(),
+                        });
+
+                        buf.extend(line);
+                    }
+                }
+            }
+        }
+
+        (buf, lines)
+    })
+}
+
+/// a line of output from a script and when it was read
+pub(crate) struct OutputLine {
+    pub time: SystemTime,
+    pub stderr: bool,
+    pub text: String,
+}
+
+/// the output of a script, `lines` has stdout and stderr in the order they
+/// were read
+pub(crate) struct ScriptOutput {
+    pub status: ExitStatus,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    pub lines: Vec<OutputLine>,
+    /// the timeout in seconds when the script was killed for going over it
+    pub timed_out: Option<u64>,
+}
+
+impl ScriptOutput {
+    /// describe how the script exited, a timeout is shown instead of the
+    /// signal that killed it
+    pub fn exit_msg(&self) -> String {
+        match self.timed_out {
+            Some(timeout) => format!("a timeout after {} seconds", timeout),
+            None => failure_msg(&self.status),
+        }
+    }
+}
+
+/// run a script with bash and return its output
+///
+/// the script is run with the environment, umask, resource limits and timeout
+/// from `exec`, going over the timeout kills the whole process group and the
+/// output has `timed_out` set
+pub(crate) fn run_script(
+    script: &str,
+    exec: &SkelExec,
+) -> Result<ScriptOutput, Box<dyn Error>> {
+    let mut child = make_command(script, exec)?.spawn()?;
+
+    // read while waiting so a full pipe does not block the script
+    let stdout = read_lines(child.stdout.take(), false);
+    let stderr = read_lines(child.stderr.take(), true);
+
+    let mut timed_out = None;
+
+    let status = match exec.timeout {
+        None => child.wait()?,
+        Some(timeout) => {
+            let deadline = Instant::now() + Duration::from_secs(timeout);
+
+            loop {
+                if let Some(status) = child.try_wait()? {
+                    break status;
+                }
+
+                if Instant::now() >= deadline {
+                    // the child is the leader of its process group
+                    unsafe {
+                        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+                    }
+
+                    timed_out = Some(timeout);
+
+                    break child.wait()?;
+                }
+
+                thread::sleep(POLL_INTERVAL);
+            }
+        }
+    };
+
+    let (stdout, mut lines) = stdout.join().unwrap_or_default();
+    let (stderr, stderr_lines) = stderr.join().unwrap_or_default();
+
+    lines.extend(stderr_lines);
+    lines.sort_by_key(|line| line.time);
+
+    Ok(ScriptOutput {
+        status,
+        stdout,
+        stderr,
+        lines,
+        timed_out,
+    })
+}
+
+/// run a script and report when it starts and finishes
+///
+/// the stdout is printed if `print_stdout` is set and all of the output goes
+/// in to the build log
+pub(crate) fn run_hook(
+    name: &str,
+    script: &str,
+    exec: &SkelExec,
+    reporter: &Reporter,
+    print_stdout: bool,
+) -> Result<ScriptOutput, Box<dyn Error>> {
+    reporter.hook_start(name);
+
+    let start = Instant::now();
+
+    match run_script(script, exec) {
+        Ok(output) => {
+            reporter.hook_finish(
+                name,
+                start.elapsed(),
+                &output.exit_msg(),
+                &output.lines,
+                print_stdout,
+            );
+
+            Ok(output)
+        }
+        Err(err) => {
+            reporter.hook_finish(
+                name,
+                start.elapsed(),
+                &err.to_string(),
+                &[],
+                print_stdout,
+            );
+
+            Err(err)
+        }
+    }
+}
+
+/// describe how a script exited, including the signal if it was killed
+pub(crate) fn failure_msg(status: &ExitStatus) -> String {
+    match (status.code(), status.signal()) {
+        (Some(code), _) => format!("exit code {}", code),
+        (None, Some(signal)) => format!("signal {}", signal),
+        _ => String::from("an unknown status"),
+    }
+}
+
+/// the error for a script that did not exit successfully, with its stderr
+pub(crate) fn build_failed(name: &str, output: &ScriptOutput) -> SkelError {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    let message = if stderr.trim().is_empty() {
+        output.exit_msg()
+    } else {
+        format!("{}\n{}", output.exit_msg(), stderr.trim_end())
+    };
+
+    SkelError::BuildFailed {
+        name: name.to_string(),
+        code: output.status.code(),
+        message,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_run_script_clear_env() {
+        env::set_var("SKEL_TEST_ALLOWED", "allowed");
+        env::set_var("SKEL_TEST_DENIED", "denied");
+
+        let exec = SkelExec {
+            clear_env: Some(true),
+            env_allow: Some(vec!["SKEL_TEST_ALLOWED".into()]),
+            ..Default::default()
+        };
+
+        let output = run_script(
+            "echo \"$SKEL_TEST_ALLOWED-${SKEL_TEST_DENIED:-unset}\"",
+            &exec,
+        )
+        .expect("did not run script");
+
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "allowed-unset\n");
+    }
+
+    #[test]
+    fn test_run_script_timeout() {
+        let exec = SkelExec {
+            timeout: Some(1),
+            ..Default::default()
+        };
+
+        let start = Instant::now();
+
+        // the background sleep is in the same process group and should be
+        // killed with the script
+        let output = run_script("sleep 30 & sleep 30", &exec)
+            .expect("did not run script");
+
+        assert_eq!(output.timed_out, Some(1), "script did not time out");
+
+        assert!(
+            start.elapsed() < Duration::from_secs(10),
+            "timeout did not kill the script"
+        );
+
+        // a timeout is a failed build like any other
+        let err = build_failed("build script", &output);
+
+        assert_eq!(err.exit_code(), 8);
+        assert!(err.to_string().contains("a timeout after 1 seconds"));
+    }
+
+    #[test]
+    fn test_run_script_umask() {
+        let exec = SkelExec {
+            umask: Some("027".into()),
+            limits: Some("strict".into()),
+            ..Default::default()
+        };
+
+        let output = run_script("umask", &exec).expect("did not run script");
+
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "0027\n");
+    }
+
+    #[test]
+    fn test_check_exec() {
+        let bad_umask = SkelExec {
+            umask: Some("999".into()),
+            ..Default::default()
+        };
+
+        assert!(check_exec(&bad_umask).is_err(), "allowed a bad umask");
+
+        let bad_limits = SkelExec {
+            limits: Some("tiny".into()),
+            ..Default::default()
+        };
+
+        assert!(check_exec(&bad_limits).is_err(), "allowed a bad profile");
+    }
+}
@@ -0,0 +1,604 @@
+use std::{
+    collections::HashMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    config::{resolve_config, RunConfig, SkelExec},
+    error::SkelError,
+    output::{copy_to_output, DirOutput, Output, WorkDir},
+    project_tree::{
+        is_empty_dir, make_project_tree, needs_dir, normalize_path,
+        plan_project_tree, Plan,
+    },
+    report::Verbosity,
+    sources::SourceMode,
+};
+
+/// what happened when a project was generated
+#[derive(Clone, Debug)]
+pub struct Report {
+    pub plan: Plan,
+    pub dry_run: bool,
+    /// every path made or changed in the order it happened, empty for a dry
+    /// run, for an output that is not a dir the paths are relative to the
+    /// project
+    pub made: Vec<PathBuf>,
+}
+
+/// make a project from a skeleton without going through the cli
+///
+/// ```no_run
+/// use skel::generator::Generator;
+///
+/// let report = Generator::new()
+///     .skeleton_path("/path/to/skeleton.toml")
+///     .name("new-project")
+///     .root("/path/to/projects")
+///     .var("author", "someone")
+///     .generate()?;
+///
+/// for path in report.made {
+///     println!("{}", path.display());
+/// }
+/// # Ok::<(), skel::error::SkelError>(())
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Generator {
+    pub(crate) skeleton: Option<String>,
+    pub(crate) skeleton_path: Option<PathBuf>,
+    pub(crate) skeleton_text: Option<String>,
+    pub(crate) config_path: Option<PathBuf>,
+    pub(crate) name: Option<String>,
+    pub(crate) target: Option<PathBuf>,
+    pub(crate) root: Option<PathBuf>,
+    pub(crate) parents: bool,
+    pub(crate) vars: HashMap<String, String>,
+    pub(crate) dry_run: bool,
+    pub(crate) no_build: bool,
+    pub(crate) trust: bool,
+    pub(crate) exec: SkelExec,
+    pub(crate) verbosity: Verbosity,
+    pub(crate) log_file: Option<PathBuf>,
+    pub(crate) skip_name_check: bool,
+    /// `{{root}}` instead of the full path to the project
+    pub(crate) root_var: Option<String>,
+}
+
+impl Generator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// a skeleton name or alias from the main config
+    pub fn skeleton<S: Into<String>>(mut self, skeleton: S) -> Self {
+        self.skeleton = Some(skeleton.into());
+        self
+    }
+
+    /// a skeleton file to use instead of looking one up in the main config
+    pub fn skeleton_path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.skeleton_path = Some(path.into());
+        self
+    }
+
+    /// a skeleton given as text like one read from stdin, it is shown as
+    /// `<stdin>` and `skel-dir` is the current dir
+    pub fn skeleton_text<S: Into<String>>(mut self, text: S) -> Self {
+        self.skeleton_text = Some(text.into());
+        self
+    }
+
+    /// a main config to use instead of `$XDG_CONFIG_HOME/skel/config.toml`
+    pub fn config_path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.config_path = Some(path.into());
+        self
+    }
+
+    /// the name of the new project, the last part of the target by default
+    pub fn name<S: Into<String>>(mut self, name: S) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// the dir to make the project in, relative to the root and the name by
+    /// default, `.` makes the project in the root itself
+    pub fn target<P: Into<PathBuf>>(mut self, target: P) -> Self {
+        self.target = Some(target.into());
+        self
+    }
+
+    /// the dir the target is in, the current dir by default
+    pub fn root<P: Into<PathBuf>>(mut self, root: P) -> Self {
+        self.root = Some(root.into());
+        self
+    }
+
+    /// make the root and any missing parents instead of failing
+    pub fn parents(mut self, parents: bool) -> Self {
+        self.parents = parents;
+        self
+    }
+
+    /// a template variable, these can not replace `name`, `root`,
+    /// `config-dir` or `skel-dir` but do win over `derived` variables
+    pub fn var<K: Into<String>, V: Into<String>>(
+        mut self,
+        key: K,
+        value: V,
+    ) -> Self {
+        self.vars.insert(key.into(), value.into());
+        self
+    }
+
+    /// print what would be made instead of making it
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// skip the build script and run steps
+    pub fn no_build(mut self, no_build: bool) -> Self {
+        self.no_build = no_build;
+        self
+    }
+
+    /// run scripts without asking to trust them
+    pub fn trust(mut self, trust: bool) -> Self {
+        self.trust = trust;
+        self
+    }
+
+    /// exec options that win over the ones in the skeleton
+    pub fn exec(mut self, exec: SkelExec) -> Self {
+        self.exec = exec;
+        self
+    }
+
+    pub fn verbosity(mut self, verbosity: Verbosity) -> Self {
+        self.verbosity = verbosity;
+        self
+    }
+
+    /// write the build output to a file, a relative path is put in the
+    /// project
+    pub fn log_file<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.log_file = Some(path.into());
+        self
+    }
+
+    /// do not check the name against `name_pattern`, for a preview with a
+    /// made up name
+    pub(crate) fn skip_name_check(mut self, skip: bool) -> Self {
+        self.skip_name_check = skip;
+        self
+    }
+
+    // the project dir and the project name
+    fn project_root(&self) -> Result<(PathBuf, String), SkelError> {
+        let current_dir =
+            env::current_dir().map_err(|e| SkelError::io(Path::new("."), e))?;
+
+        let base = match self.root {
+            Some(ref root) if root.is_dir() || self.parents => {
+                current_dir.join(root)
+            }
+            Some(ref root) => {
+                return Err(SkelError::Other(format!(
+                    "different root does not exists or is not a dir {}, use \
+                    --parents to make it",
+                    root.display()
+                )))
+            }
+            None => current_dir,
+        };
+
+        let target = self
+            .target
+            .as_deref()
+            .or_else(|| self.name.as_deref().map(Path::new))
+            .ok_or("did not get a name or target for the new project")?;
+
+        let root = normalize_path(&base.join(target));
+
+        let name = match (&self.name, root.file_name()) {
+            (Some(name), _) => name.clone(),
+            (None, Some(file_name)) => file_name.to_string_lossy().to_string(),
+            (None, None) => {
+                return Err(SkelError::Other(format!(
+                    "could not get a project name from {}, give one with \
+                    --name",
+                    root.display()
+                )))
+            }
+        };
+
+        if name.contains('/') {
+            return Err(SkelError::Other(format!(
+                "project name {} can not have a /, give the path as the \
+                target and the name with --name",
+                name
+            )));
+        }
+
+        Ok((root, name))
+    }
+
+    /// the name of the project, given or taken from the target
+    pub fn project_name(&self) -> Result<String, SkelError> {
+        self.project_root().map(|(_, name)| name)
+    }
+
+    fn run_config(&self, sources: SourceMode) -> Result<RunConfig, SkelError> {
+        let (root, name) = self.project_root()?;
+
+        resolve_config(self, root, name, sources)
+    }
+
+    // git sources are only fetched and locked when the project is made
+    fn source_mode(&self) -> SourceMode {
+        if self.dry_run {
+            SourceMode::Cached
+        } else {
+            SourceMode::Fetch
+        }
+    }
+
+    /// resolve the skeleton in to a plan without making anything, git sources
+    /// are only read from the cache
+    pub fn plan(&self) -> Result<Plan, SkelError> {
+        plan_project_tree(&self.run_config(SourceMode::Cached)?)
+    }
+
+    /// make the project in a dir, or print the plan for a dry run
+    pub fn generate(&self) -> Result<Report, SkelError> {
+        let (root, _) = self.project_root()?;
+
+        self.generate_into(&mut DirOutput::new(root))
+    }
+
+    /// make the project in to any output, or print the plan for a dry run
+    ///
+    /// an output that is not a dir on disk can not run scripts, so a skeleton
+    /// that needs a dir is made in a temp dir first and then copied in to the
+    /// output
+    pub fn generate_into(
+        &self,
+        output: &mut dyn Output,
+    ) -> Result<Report, SkelError> {
+        let out_dir = output.dir().map(Path::to_path_buf);
+
+        if let (Some(ref dir), false) = (&out_dir, self.dry_run) {
+            if dir.exists() && !is_empty_dir(dir) {
+                return Err(SkelError::Other(format!(
+                    "project exists and is not an empty dir {}",
+                    dir.display()
+                )));
+            }
+
+            if let (Some(parent), true) = (dir.parent(), self.parents) {
+                fs::create_dir_all(parent)
+                    .map_err(|e| SkelError::io(parent, e))?;
+            }
+        }
+
+        // in an output that is not a dir `{{root}}` is the name of the project
+        // so the path it was made in is not kept in the files
+        let mut gen = self.clone();
+
+        if out_dir.is_none() {
+            gen.root_var = Some(self.project_name()?);
+        }
+
+        let run_conf = gen.run_config(gen.source_mode())?;
+
+        let plan = plan_project_tree(&run_conf)?;
+
+        let made = if self.dry_run || out_dir.is_some() {
+            make_project_tree(self.dry_run, &plan, &run_conf, output)?;
+
+            run_conf.reporter.take_made()
+        } else if needs_dir(&run_conf) {
+            gen.generate_in_work_dir(output)?
+        } else {
+            make_project_tree(false, &plan, &run_conf, output)?;
+
+            // there is no dir on disk so the paths are relative to the project
+            run_conf
+                .reporter
+                .take_made()
+                .into_iter()
+                .filter_map(|path| {
+                    path.strip_prefix(&plan.root).ok().map(Path::to_path_buf)
+                })
+                .collect()
+        };
+
+        Ok(Report {
+            plan,
+            dry_run: self.dry_run,
+            made,
+        })
+    }
+
+    // make the project in a temp dir and copy it in to the output
+    fn generate_in_work_dir(
+        &self,
+        output: &mut dyn Output,
+    ) -> Result<Vec<PathBuf>, SkelError> {
+        let (_, name) = self.project_root()?;
+
+        let work_dir = WorkDir::new()?;
+        let work_root = work_dir.path.join(&name);
+
+        let mut work_gen = self.clone();
+
+        work_gen.exec.work_dir = Some(work_dir.path.clone());
+
+        let work_conf = resolve_config(
+            &work_gen,
+            work_root.clone(),
+            name,
+            SourceMode::Fetch,
+        )?;
+        let work_plan = plan_project_tree(&work_conf)?;
+
+        make_project_tree(
+            false,
+            &work_plan,
+            &work_conf,
+            &mut DirOutput::new(&work_root),
+        )?;
+
+        let copied = copy_to_output(&work_root, output)?;
+
+        output.finish().map_err(|e| SkelError::io(&work_root, e))?;
+
+        Ok(copied)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::fs;
+
+    use crate::{
+        output::{MemoryEntry, MemoryOutput},
+        project_tree::EntryKind,
+        test_utils,
+    };
+
+    // a main config next to the test projects dir so `{{config-dir}}` points
+    // at the test skeleton
+    fn test_config_path(test_data: &test_utils::TestData) -> PathBuf {
+        let config_path = test_data.temp_path.join("config.toml");
+
+        fs::write(&config_path, test_utils::TEST_CONFIG)
+            .expect("could not make test config");
+
+        config_path
+    }
+
+    #[test]
+    fn test_generator_plan() {
+        let mut test_data = test_utils::TestData::default();
+
+        test_data.make_configs();
+
+        let plan = test_utils::test_generator()
+            .config_path(test_config_path(&test_data))
+            .root(&test_data.temp_path)
+            .plan()
+            .expect("did not make a plan");
+
+        let root = test_data.temp_path.join("test_project");
+
+        assert_eq!(plan.root, root);
+
+        let template = plan
+            .entries
+            .get(&root.join("test_src/test_template_file.txt"))
+            .expect("did not plan the template");
+
+        assert_eq!(
+            template.kind,
+            EntryKind::Template(
+                "this is a test template for test_project".into()
+            )
+        );
+
+        assert!(!root.exists(), "a plan made the project");
+    }
+
+    #[test]
+    fn test_generator_project_root() {
+        let test_data = test_utils::TestData::default();
+
+        let root = &test_data.temp_path;
+
+        let (project, name) = Generator::new()
+            .root(root)
+            .target("nested/dir")
+            .project_root()
+            .expect("did not get the project root for a nested target");
+
+        assert_eq!((project, name.as_str()), (root.join("nested/dir"), "dir"));
+
+        let (project, name) = Generator::new()
+            .root(root.join("empty"))
+            .parents(true)
+            .target(".")
+            .name("some_name")
+            .project_root()
+            .expect("did not get the project root for `.`");
+
+        assert_eq!((project, name.as_str()), (root.join("empty"), "some_name"));
+
+        assert!(
+            Generator::new()
+                .root(root.join("missing"))
+                .name("test_project")
+                .project_root()
+                .is_err(),
+            "used a root that does not exist without parents"
+        );
+
+        assert!(
+            Generator::new()
+                .root(root)
+                .name("a/b")
+                .project_root()
+                .is_err(),
+            "allowed a / in the project name"
+        );
+    }
+
+    #[test]
+    fn test_generator_without_main_config() {
+        let test_data = test_utils::TestData::default();
+
+        let missing_config = test_data.temp_path.join("missing.toml");
+
+        let plan = Generator::new()
+            .skeleton_text(
+                "[[templates]]\npath = \"dir\"\ntemplate = \"{{config-dir}}\"",
+            )
+            .config_path(&missing_config)
+            .name("test_project")
+            .root(&test_data.temp_path)
+            .plan()
+            .expect("did not make a plan without a main config");
+
+        let template = &plan.entries[&plan.root.join("dir")];
+
+        assert_eq!(
+            template.kind,
+            EntryKind::Template(
+                env::current_dir().unwrap().to_string_lossy().to_string()
+            ),
+            "config-dir is not the skeleton dir"
+        );
+
+        assert!(
+            matches!(
+                Generator::new()
+                    .skeleton("t")
+                    .config_path(&missing_config)
+                    .name("test_project")
+                    .plan(),
+                Err(SkelError::ConfigNotFound { .. })
+            ),
+            "looked up a skeleton without a main config"
+        );
+    }
+
+    #[test]
+    fn test_generator_generate() {
+        let mut test_data = test_utils::TestData::default();
+
+        test_data.make_configs();
+
+        let report = test_utils::test_generator()
+            .config_path(test_config_path(&test_data))
+            .root(&test_data.temp_path)
+            .dry_run(false)
+            .verbosity(Verbosity::Quiet)
+            .generate()
+            .expect("did not generate the project");
+
+        let root = test_data.temp_path.join("test_project");
+
+        assert!(root.join("test_src/test_main.rs").is_file());
+
+        assert_eq!(
+            report.made.len(),
+            report.plan.entries.len(),
+            "did not report everything made"
+        );
+
+        assert!(
+            test_utils::test_generator()
+                .config_path(test_config_path(&test_data))
+                .root(&test_data.temp_path)
+                .dry_run(false)
+                .generate()
+                .is_err(),
+            "made a project that already exists"
+        );
+    }
+
+    #[test]
+    fn test_generator_generate_into_memory() {
+        let mut test_data = test_utils::TestData::default();
+
+        test_data.make_configs();
+
+        let mut output = MemoryOutput::default();
+
+        let report = test_utils::test_generator()
+            .config_path(test_config_path(&test_data))
+            .root(&test_data.temp_path)
+            .dry_run(false)
+            .verbosity(Verbosity::Quiet)
+            .generate_into(&mut output)
+            .expect("did not generate the project in to memory");
+
+        assert!(
+            !test_data.temp_path.join("test_project").exists(),
+            "wrote to disk with a memory output"
+        );
+
+        match output
+            .entries
+            .get(Path::new("test_src/test_template_file.txt"))
+        {
+            Some(MemoryEntry::File { contents, .. }) => assert_eq!(
+                contents,
+                b"this is a test template for test_project"
+            ),
+            _ => panic!("did not write the template in to memory"),
+        }
+
+        assert!(report.made.iter().all(|path| path.is_relative()));
+    }
+
+    #[test]
+    fn test_generator_root_var_in_archive() {
+        let test_data = test_utils::TestData::default();
+
+        let mut output = MemoryOutput::default();
+
+        // the steps need a dir so the project is made in a work dir first
+        Generator::new()
+            .skeleton_text(
+                "[[templates]]\n\
+                path = \"{{root}}/root.txt\"\n\
+                template = \"{{root}}\"\n\
+                [[steps]]\n\
+                run = \"echo hi > {{root}}/ran.txt\"\n",
+            )
+            .config_path(test_config_path(&test_data))
+            .name("test_project")
+            .root(&test_data.temp_path)
+            .trust(true)
+            .verbosity(Verbosity::Quiet)
+            .generate_into(&mut output)
+            .expect("did not generate the project in to an archive");
+
+        assert!(
+            matches!(
+                output.entries.get(Path::new("root.txt")),
+                Some(MemoryEntry::File { contents, .. })
+                    if contents == b"test_project"
+            ),
+            "did not use the project name as the root"
+        );
+        assert!(
+            output.entries.contains_key(Path::new("ran.txt")),
+            "the step did not run in the project"
+        );
+    }
+}
@@ -1,7 +1,22 @@
+pub mod commands;
 pub mod config;
+pub mod error;
+pub mod generator;
+pub mod output;
+pub mod pack;
 pub mod parse_args;
+pub mod picker;
 pub mod project_tree;
+pub mod report;
+pub mod sources;
+pub mod style;
 pub mod templating;
 
+mod edits;
+mod exec;
+mod merge;
+mod steps;
+mod trust;
+
 #[cfg(test)]
 mod test_utils;
@@ -0,0 +1,459 @@
+use std::{error::Error, fs, path::Path};
+
+use serde_json::Value as JsonValue;
+use serde_yaml::Value as YamlValue;
+use toml_edit::{Document, Item, Table, Value as TomlValue};
+
+use crate::{
+    config::{RunConfig, SkelMerge},
+    project_tree::{check_symlinks, normalize_path, project_path},
+    style::{paint, Stream, Style},
+};
+
+// the formats that can be merged
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Format {
+    Toml,
+    Json,
+    Yaml,
+}
+
+fn merge_source(index: usize, merge: &SkelMerge) -> String {
+    format!("merge[{}] \"{}\"", index, merge.path)
+}
+
+// use the given format or guess it from the file extension
+fn merge_format(
+    source: &str,
+    merge: &SkelMerge,
+) -> Result<Format, Box<dyn Error>> {
+    let format = match merge.format {
+        Some(ref format) => format.as_str(),
+        None => Path::new(&merge.path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default(),
+    };
+
+    match format {
+        "toml" => Ok(Format::Toml),
+        "json" => Ok(Format::Json),
+        "yaml" | "yml" => Ok(Format::Yaml),
+        _ => Err(Box::from(format!(
+            "{} needs a format of toml, json or yaml",
+            source
+        ))),
+    }
+}
+
+// should arrays be appended to or replaced, appending is the default
+fn merge_appends(
+    source: &str,
+    merge: &SkelMerge,
+) -> Result<bool, Box<dyn Error>> {
+    match merge.arrays.as_deref() {
+        None | Some("append") => Ok(true),
+        Some("replace") => Ok(false),
+        Some(other) => Err(Box::from(format!(
+            "{} arrays should be append or replace not {}",
+            source, other
+        ))),
+    }
+}
+
+// the fragment to merge from the `content` string or the `include` file
+fn merge_fragment(
+    source: &str,
+    merge: &SkelMerge,
+    run_conf: &RunConfig,
+) -> Result<String, Box<dyn Error>> {
+    match (merge.content.as_ref(), merge.include.as_ref()) {
+        (Some(content), None) => Ok(content.clone()),
+        (None, Some(include)) => {
+            let include_string = fs::read_to_string(include).map_err(|e| {
+                format!("{} could not read include {} {}", source, include, e)
+            })?;
+
+            Ok(run_conf.engine.render(
+                include,
+                &include_string,
+                &run_conf.template_data,
+            )?)
+        }
+        _ => Err(Box::from(format!(
+            "{} needs one of content or include",
+            source
+        ))),
+    }
+}
+
+// copy a table without its position so it is written after the tables that
+// are already in the document instead of where it was in the fragment
+fn without_position(item: Item) -> Item {
+    match item {
+        Item::Table(table) => {
+            let mut new_table = Table::new();
+
+            new_table.set_implicit(table.is_implicit());
+            new_table.set_dotted(table.is_dotted());
+            *new_table.decor_mut() = table.decor().clone();
+
+            for (key, value) in table.into_iter() {
+                new_table.insert(&key, without_position(value));
+            }
+
+            Item::Table(new_table)
+        }
+        Item::ArrayOfTables(tables) => {
+            let mut new_tables = toml_edit::ArrayOfTables::new();
+
+            for table in tables.into_iter() {
+                if let Item::Table(table) = without_position(Item::Table(table))
+                {
+                    new_tables.push(table);
+                }
+            }
+
+            Item::ArrayOfTables(new_tables)
+        }
+        other => other,
+    }
+}
+
+fn merge_toml_item(target: &mut Item, fragment: Item, append: bool) {
+    if let (Some(target_table), true) =
+        (target.as_table_like_mut(), fragment.is_table_like())
+    {
+        let entries: Vec<(String, Item)> = match fragment {
+            Item::Table(table) => table
+                .into_iter()
+                .map(|(key, item)| (key.to_string(), item))
+                .collect(),
+            Item::Value(TomlValue::InlineTable(table)) => table
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), Item::Value(value)))
+                .collect(),
+            _ => vec![],
+        };
+
+        for (key, item) in entries {
+            match target_table.get_mut(&key) {
+                Some(existing) => merge_toml_item(existing, item, append),
+                None => {
+                    target_table.insert(&key, without_position(item));
+                }
+            }
+        }
+
+        return;
+    }
+
+    match (target, fragment) {
+        (
+            Item::Value(TomlValue::Array(target_array)),
+            Item::Value(TomlValue::Array(fragment_array)),
+        ) if append => {
+            for value in fragment_array.into_iter() {
+                target_array.push(value);
+            }
+        }
+        (Item::ArrayOfTables(target_tables), Item::ArrayOfTables(tables))
+            if append =>
+        {
+            for table in tables.into_iter() {
+                if let Item::Table(table) = without_position(Item::Table(table))
+                {
+                    target_tables.push(table);
+                }
+            }
+        }
+        // keep the spacing and comments around the old value
+        (Item::Value(target_value), Item::Value(mut value)) => {
+            *value.decor_mut() = target_value.decor().clone();
+
+            *target_value = value;
+        }
+        (target, fragment) => *target = without_position(fragment),
+    }
+}
+
+fn merge_toml(
+    text: &str,
+    fragment: &str,
+    append: bool,
+) -> Result<String, Box<dyn Error>> {
+    let mut document: Document = text.parse()?;
+    let fragment: Document = fragment.parse()?;
+
+    merge_toml_item(
+        document.as_item_mut(),
+        Item::Table(fragment.as_table().clone()),
+        append,
+    );
+
+    Ok(document.to_string())
+}
+
+fn merge_json_value(target: &mut JsonValue, fragment: JsonValue, append: bool) {
+    match (target, fragment) {
+        (JsonValue::Object(target_map), JsonValue::Object(fragment_map)) => {
+            for (key, value) in fragment_map {
+                match target_map.get_mut(&key) {
+                    Some(existing) => merge_json_value(existing, value, append),
+                    None => {
+                        target_map.insert(key, value);
+                    }
+                }
+            }
+        }
+        (JsonValue::Array(target_array), JsonValue::Array(fragment_array))
+            if append =>
+        {
+            target_array.extend(fragment_array);
+        }
+        (target, fragment) => *target = fragment,
+    }
+}
+
+fn merge_json(
+    text: &str,
+    fragment: &str,
+    append: bool,
+) -> Result<String, Box<dyn Error>> {
+    let mut document: JsonValue = serde_json::from_str(text)?;
+    let fragment: JsonValue = serde_json::from_str(fragment)?;
+
+    merge_json_value(&mut document, fragment, append);
+
+    let mut merged = serde_json::to_string_pretty(&document)?;
+
+    merged.push('\n');
+
+    Ok(merged)
+}
+
+fn merge_yaml_value(target: &mut YamlValue, fragment: YamlValue, append: bool) {
+    match (target, fragment) {
+        (YamlValue::Mapping(target_map), YamlValue::Mapping(fragment_map)) => {
+            for (key, value) in fragment_map {
+                match target_map.get_mut(&key) {
+                    Some(existing) => merge_yaml_value(existing, value, append),
+                    None => {
+                        target_map.insert(key, value);
+                    }
+                }
+            }
+        }
+        (
+            YamlValue::Sequence(target_seq),
+            YamlValue::Sequence(fragment_seq),
+        ) if append => {
+            target_seq.extend(fragment_seq);
+        }
+        (target, fragment) => *target = fragment,
+    }
+}
+
+fn merge_yaml(
+    text: &str,
+    fragment: &str,
+    append: bool,
+) -> Result<String, Box<dyn Error>> {
+    let mut document: YamlValue = serde_yaml::from_str(text)?;
+    let fragment: YamlValue = serde_yaml::from_str(fragment)?;
+
+    merge_yaml_value(&mut document, fragment, append);
+
+    Ok(serde_yaml::to_string(&document)?)
+}
+
+fn merge_text(
+    format: Format,
+    text: &str,
+    fragment: &str,
+    append: bool,
+) -> Result<String, Box<dyn Error>> {
+    match format {
+        Format::Toml => merge_toml(text, fragment, append),
+        Format::Json => merge_json(text, fragment, append),
+        Format::Yaml => merge_yaml(text, fragment, append),
+    }
+}
+
+/// deep merge the `merge` list in to files already in the project
+///
+/// tables are merged and arrays are appended to or replaced, toml keeps its
+/// formatting and comments, json and yaml are written back out pretty printed
+pub(crate) fn run_merges(run_conf: &RunConfig) -> Result<(), Box<dyn Error>> {
+    let merges = match run_conf.skel_conf.merge {
+        Some(ref merges) => merges,
+        None => return Ok(()),
+    };
+
+    let root = normalize_path(&run_conf.root_path);
+
+    for (i, merge) in merges.iter().enumerate() {
+        let source = merge_source(i, merge);
+
+        let format = merge_format(&source, merge)?;
+        let append = merge_appends(&source, merge)?;
+        let fragment = merge_fragment(&source, merge, run_conf)?;
+
+        let path = project_path(&root, &run_conf.template_data, &merge.path)?;
+
+        check_symlinks(&root, &path)?;
+
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(_) if merge.optional.unwrap_or(false) => continue,
+            Err(e) => {
+                return Err(Box::from(format!(
+                    "{} could not read {} {}",
+                    source,
+                    path.display(),
+                    e
+                )))
+            }
+        };
+
+        let merged = merge_text(format, &text, &fragment, append)
+            .map_err(|e| format!("{} could not merge {}", source, e))?;
+
+        fs::write(&path, merged)?;
+
+        run_conf.reporter.created("merged", &path);
+    }
+
+    Ok(())
+}
+
+/// print the `merge` list in the order it would be applied
+pub(crate) fn print_merges(run_conf: &RunConfig) -> Result<(), Box<dyn Error>> {
+    let merges = match run_conf.skel_conf.merge {
+        Some(ref merges) => merges,
+        None => return Ok(()),
+    };
+
+    let root = normalize_path(&run_conf.root_path);
+
+    run_conf.reporter.info("  merge:");
+
+    for (i, merge) in merges.iter().enumerate() {
+        let source = merge_source(i, merge);
+
+        let format = merge_format(&source, merge)?;
+        let append = merge_appends(&source, merge)?;
+        let fragment = merge_fragment(&source, merge, run_conf)?;
+
+        let path = project_path(&root, &run_conf.template_data, &merge.path)?;
+
+        run_conf.reporter.info(&format!(
+            "  {:>3}. {} arrays {} -> {}",
+            i,
+            paint(Style::Kind, Stream::Stdout, &format!("{:?}", format)),
+            if append { "append" } else { "replace" },
+            path.display()
+        ));
+
+        for line in fragment.lines() {
+            run_conf.reporter.info(&format!("    {}", line));
+        }
+    }
+
+    run_conf.reporter.info("  ------");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TEST_TOML: &str = r#"# the package
+[package]
+name = "test"  # the name
+
+[dependencies]
+serde = "1"
+
+[features]
+default = ["a"]
+"#;
+
+    #[test]
+    fn test_merge_toml() {
+        let fragment = r#"
+[package]
+name = "test-cli"
+
+[dependencies]
+regex = "1"
+
+[features]
+default = ["b"]
+
+[dev-dependencies]
+tempfile = "3"
+"#;
+
+        let merged = merge_toml(TEST_TOML, fragment, true).unwrap();
+
+        assert_eq!(
+            merged,
+            r#"# the package
+[package]
+name = "test-cli"  # the name
+
+[dependencies]
+serde = "1"
+regex = "1"
+
+[features]
+default = ["a", "b"]
+
+[dev-dependencies]
+tempfile = "3"
+"#
+        );
+
+        let replaced = merge_toml(TEST_TOML, fragment, false).unwrap();
+
+        assert!(
+            replaced.contains("default = [\"b\"]"),
+            "did not replace the array {}",
+            replaced
+        );
+    }
+
+    #[test]
+    fn test_merge_json() {
+        let text =
+            r#"{"name": "test", "scripts": {"test": "jest"}, "files": ["a"]}"#;
+
+        let fragment = r#"{"scripts": {"lint": "eslint ."}, "files": ["b"]}"#;
+
+        let merged: JsonValue =
+            serde_json::from_str(&merge_json(text, fragment, true).unwrap())
+                .unwrap();
+
+        assert_eq!(
+            merged,
+            serde_json::json!({
+                "name": "test",
+                "scripts": {"test": "jest", "lint": "eslint ."},
+                "files": ["a", "b"],
+            })
+        );
+    }
+
+    #[test]
+    fn test_merge_yaml() {
+        let text = "name: test\nsteps:\n  - build\n";
+
+        let fragment = "steps:\n  - deploy\non: push\n";
+
+        let merged = merge_yaml(text, fragment, false).unwrap();
+
+        assert_eq!(merged, "name: test\nsteps:\n- deploy\non: push\n");
+    }
+}
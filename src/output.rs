@@ -0,0 +1,641 @@
+use std::{
+    collections::BTreeMap,
+    env,
+    error::Error,
+    fs,
+    io::{self, Write},
+    os::unix::fs::PermissionsExt,
+    path::{Component, Path, PathBuf},
+    process,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use flate2::{write::GzEncoder, Compression};
+use zip::{write::FileOptions, ZipWriter};
+
+use crate::error::SkelError;
+
+/// where a project is made
+///
+/// every path is relative to the project root and parent dirs are always made
+/// before their children
+pub trait Output {
+    fn create_dir(&mut self, path: &Path) -> io::Result<()>;
+
+    fn write_file(
+        &mut self,
+        path: &Path,
+        contents: &[u8],
+        mode: u32,
+    ) -> io::Result<()>;
+
+    /// a symlink to `target`, the target is relative to the dir of the link
+    fn create_symlink(&mut self, path: &Path, target: &Path) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "can not write the symlink {} -> {} to this output",
+                path.display(),
+                target.display()
+            ),
+        ))
+    }
+
+    /// called once after everything is written
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// the real dir the project is made in, `None` if the output is not a dir
+    /// on disk so scripts can not be run in it
+    fn dir(&self) -> Option<&Path> {
+        None
+    }
+}
+
+/// make the project in a dir on disk
+pub struct DirOutput {
+    root: PathBuf,
+}
+
+impl DirOutput {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl Output for DirOutput {
+    fn create_dir(&mut self, path: &Path) -> io::Result<()> {
+        fs::create_dir_all(self.root.join(path))
+    }
+
+    // the mode is left to the umask like any other file
+    fn write_file(
+        &mut self,
+        path: &Path,
+        contents: &[u8],
+        _mode: u32,
+    ) -> io::Result<()> {
+        fs::write(self.root.join(path), contents)
+    }
+
+    #[cfg(unix)]
+    fn create_symlink(&mut self, path: &Path, target: &Path) -> io::Result<()> {
+        std::os::unix::fs::symlink(target, self.root.join(path))
+    }
+
+    fn dir(&self) -> Option<&Path> {
+        Some(&self.root)
+    }
+}
+
+/// a single path in a `MemoryOutput`
+#[derive(Clone, Debug, PartialEq)]
+pub enum MemoryEntry {
+    Dir,
+    File { contents: Vec<u8>, mode: u32 },
+    Symlink { target: PathBuf },
+}
+
+/// keep the project in memory, for tests and previews
+#[derive(Clone, Debug, Default)]
+pub struct MemoryOutput {
+    pub entries: BTreeMap<PathBuf, MemoryEntry>,
+}
+
+impl Output for MemoryOutput {
+    fn create_dir(&mut self, path: &Path) -> io::Result<()> {
+        // the project root is not an entry
+        if path != Path::new("") {
+            self.entries.insert(path.to_path_buf(), MemoryEntry::Dir);
+        }
+
+        Ok(())
+    }
+
+    fn write_file(
+        &mut self,
+        path: &Path,
+        contents: &[u8],
+        mode: u32,
+    ) -> io::Result<()> {
+        self.entries.insert(
+            path.to_path_buf(),
+            MemoryEntry::File {
+                contents: contents.to_vec(),
+                mode,
+            },
+        );
+
+        Ok(())
+    }
+
+    fn create_symlink(&mut self, path: &Path, target: &Path) -> io::Result<()> {
+        self.entries.insert(
+            path.to_path_buf(),
+            MemoryEntry::Symlink {
+                target: target.to_path_buf(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+/// the kinds of archive that can be written
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArchiveFormat {
+    TarGz,
+    Zip,
+}
+
+impl ArchiveFormat {
+    /// guess the format from the file name
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy();
+
+        if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(ArchiveFormat::TarGz)
+        } else if name.ends_with(".zip") {
+            Some(ArchiveFormat::Zip)
+        } else {
+            None
+        }
+    }
+}
+
+enum ArchiveWriter {
+    TarGz(tar::Builder<GzEncoder<fs::File>>),
+    Zip(ZipWriter<fs::File>),
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn zip_err(err: zip::result::ZipError) -> io::Error {
+    match err {
+        zip::result::ZipError::Io(err) => err,
+        err => io::Error::other(err),
+    }
+}
+
+/// write the project in to a `.tar.gz` or `.zip` archive
+///
+/// everything in the archive is put in a `prefix` dir so it unpacks in to a
+/// single project dir, an archive that is dropped before `finish` is removed
+pub struct ArchiveOutput {
+    path: PathBuf,
+    prefix: PathBuf,
+    writer: Option<ArchiveWriter>,
+}
+
+impl ArchiveOutput {
+    pub fn create(path: &Path, prefix: &str) -> Result<Self, SkelError> {
+        let format = ArchiveFormat::from_path(path).ok_or_else(|| {
+            SkelError::Other(format!(
+                "can only write .tar.gz, .tgz or .zip archives not {}",
+                path.display()
+            ))
+        })?;
+
+        // do not replace an archive that is already there
+        let file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .map_err(|e| SkelError::io(path, e))?;
+
+        let writer = match format {
+            ArchiveFormat::TarGz => ArchiveWriter::TarGz(tar::Builder::new(
+                GzEncoder::new(file, Compression::default()),
+            )),
+            ArchiveFormat::Zip => ArchiveWriter::Zip(ZipWriter::new(file)),
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            prefix: PathBuf::from(prefix),
+            writer: Some(writer),
+        })
+    }
+
+    fn writer(&mut self) -> io::Result<&mut ArchiveWriter> {
+        self.writer
+            .as_mut()
+            .ok_or_else(|| io::Error::other("archive is already finished"))
+    }
+}
+
+impl Output for ArchiveOutput {
+    fn create_dir(&mut self, path: &Path) -> io::Result<()> {
+        let path = self.prefix.join(path);
+
+        match self.writer()? {
+            ArchiveWriter::TarGz(builder) => {
+                let mut header = tar::Header::new_gnu();
+
+                header.set_entry_type(tar::EntryType::Directory);
+                header.set_mode(0o755);
+                header.set_size(0);
+                header.set_mtime(now_secs());
+
+                builder.append_data(&mut header, path, io::empty())
+            }
+            ArchiveWriter::Zip(zip) => zip
+                .add_directory(
+                    path.to_string_lossy(),
+                    FileOptions::default().unix_permissions(0o755),
+                )
+                .map_err(zip_err),
+        }
+    }
+
+    fn write_file(
+        &mut self,
+        path: &Path,
+        contents: &[u8],
+        mode: u32,
+    ) -> io::Result<()> {
+        let path = self.prefix.join(path);
+
+        match self.writer()? {
+            ArchiveWriter::TarGz(builder) => {
+                let mut header = tar::Header::new_gnu();
+
+                header.set_entry_type(tar::EntryType::Regular);
+                header.set_mode(mode);
+                header.set_size(contents.len() as u64);
+                header.set_mtime(now_secs());
+
+                builder.append_data(&mut header, path, contents)
+            }
+            ArchiveWriter::Zip(zip) => {
+                zip.start_file(
+                    path.to_string_lossy(),
+                    FileOptions::default().unix_permissions(mode),
+                )
+                .map_err(zip_err)?;
+
+                zip.write_all(contents)
+            }
+        }
+    }
+
+    fn create_symlink(&mut self, path: &Path, target: &Path) -> io::Result<()> {
+        let path = self.prefix.join(path);
+
+        match self.writer()? {
+            ArchiveWriter::TarGz(builder) => {
+                let mut header = tar::Header::new_gnu();
+
+                header.set_entry_type(tar::EntryType::Symlink);
+                header.set_mode(0o777);
+                header.set_size(0);
+                header.set_mtime(now_secs());
+
+                builder.append_link(&mut header, path, target)
+            }
+            ArchiveWriter::Zip(zip) => zip
+                .add_symlink(
+                    path.to_string_lossy(),
+                    target.to_string_lossy(),
+                    FileOptions::default(),
+                )
+                .map_err(zip_err),
+        }
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        match self.writer.take() {
+            Some(ArchiveWriter::TarGz(builder)) => {
+                builder.into_inner()?.finish()?;
+            }
+            Some(ArchiveWriter::Zip(mut zip)) => {
+                zip.finish().map_err(zip_err)?;
+            }
+            None => {}
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for ArchiveOutput {
+    fn drop(&mut self) {
+        // a half written archive is not useful to anyone
+        if self.writer.take().is_some() {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// a temp dir that is removed when it is dropped
+pub(crate) struct WorkDir {
+    pub path: PathBuf,
+}
+
+impl WorkDir {
+    pub fn new() -> Result<Self, SkelError> {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or_default();
+
+        let path = env::temp_dir().join(format!(
+            "skel-{}-{}-{}",
+            process::id(),
+            now_secs(),
+            nanos
+        ));
+
+        fs::create_dir(&path).map_err(|e| SkelError::io(&path, e))?;
+
+        Ok(Self { path })
+    }
+}
+
+impl Drop for WorkDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+// a link at `link` in the project that points to `target` stays in the project
+pub(crate) fn link_in_root(link: &Path, target: &Path) -> bool {
+    let mut depth = link.components().count().saturating_sub(1);
+
+    for component in target.components() {
+        match component {
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => {}
+            Component::ParentDir if depth > 0 => depth -= 1,
+            _ => return false,
+        }
+    }
+
+    true
+}
+
+// copy everything under `dir` in to the output, `relative` is where `dir` is
+// in the project
+fn copy_dir(
+    dir: &Path,
+    relative: &Path,
+    output: &mut dyn Output,
+    copied: &mut Vec<PathBuf>,
+) -> Result<(), Box<dyn Error>> {
+    let mut entries = fs::read_dir(dir)
+        .map_err(|e| SkelError::io(dir, e))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // sort so the output is the same every time
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let path = entry.path();
+        let relative = relative.join(entry.file_name());
+
+        let metadata =
+            fs::symlink_metadata(&path).map_err(|e| SkelError::io(&path, e))?;
+
+        if metadata.is_dir() {
+            output.create_dir(&relative)?;
+            copied.push(relative.clone());
+
+            copy_dir(&path, &relative, output, copied)?;
+        } else if metadata.is_file() {
+            let contents =
+                fs::read(&path).map_err(|e| SkelError::io(&path, e))?;
+
+            output.write_file(
+                &relative,
+                &contents,
+                metadata.permissions().mode() & 0o777,
+            )?;
+
+            copied.push(relative);
+        } else if metadata.file_type().is_symlink() {
+            let target =
+                fs::read_link(&path).map_err(|e| SkelError::io(&path, e))?;
+
+            if !link_in_root(&relative, &target) {
+                return Err(Box::from(format!(
+                    "can not copy the symlink {} in to the output, it points \
+                    outside the project to {}",
+                    relative.display(),
+                    target.display()
+                )));
+            }
+
+            output.create_symlink(&relative, &target)?;
+
+            copied.push(relative);
+        } else {
+            return Err(Box::from(format!(
+                "can not copy {} in to the output, only files, dirs and \
+                symlinks can be",
+                relative.display()
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// copy a project made on disk in to an output, returns every copied path
+pub(crate) fn copy_to_output(
+    root: &Path,
+    output: &mut dyn Output,
+) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut copied = vec![];
+
+    output.create_dir(Path::new(""))?;
+
+    copy_dir(root, Path::new(""), output, &mut copied)?;
+
+    Ok(copied)
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Read;
+
+    use flate2::read::GzDecoder;
+
+    use super::*;
+
+    use crate::test_utils;
+
+    #[test]
+    fn test_copy_to_output() {
+        let test_data = test_utils::TestData::default();
+
+        let root = test_data.temp_path.join("project");
+
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(root.join("run.sh"), "echo hi").unwrap();
+        fs::set_permissions(
+            root.join("run.sh"),
+            fs::Permissions::from_mode(0o755),
+        )
+        .unwrap();
+
+        let mut output = MemoryOutput::default();
+
+        let copied = copy_to_output(&root, &mut output).unwrap();
+
+        assert_eq!(copied.len(), 3, "{:?}", copied);
+
+        assert_eq!(
+            output.entries.get(Path::new("src")),
+            Some(&MemoryEntry::Dir)
+        );
+
+        assert!(
+            matches!(
+                output.entries.get(Path::new("src/main.rs")),
+                Some(MemoryEntry::File { contents, .. }) if contents == b"fn main() {}"
+            ),
+            "did not copy the file"
+        );
+
+        assert!(
+            matches!(
+                output.entries.get(Path::new("run.sh")),
+                Some(MemoryEntry::File { mode: 0o755, .. })
+            ),
+            "did not keep the mode"
+        );
+    }
+
+    #[test]
+    fn test_archive_output() {
+        let test_data = test_utils::TestData::default();
+
+        let archive_path = test_data.temp_path.join("project.tar.gz");
+
+        let mut output =
+            ArchiveOutput::create(&archive_path, "project").unwrap();
+
+        output.create_dir(Path::new("")).unwrap();
+        output.create_dir(Path::new("src")).unwrap();
+        output
+            .write_file(Path::new("src/main.rs"), b"fn main() {}", 0o644)
+            .unwrap();
+        output.finish().unwrap();
+
+        let mut archive = tar::Archive::new(GzDecoder::new(
+            fs::File::open(&archive_path).unwrap(),
+        ));
+
+        let mut names = vec![];
+        let mut contents = String::new();
+
+        for entry in archive.entries().unwrap() {
+            let mut entry = entry.unwrap();
+
+            let path = entry.path().unwrap().to_path_buf();
+
+            if path == Path::new("project/src/main.rs") {
+                entry.read_to_string(&mut contents).unwrap();
+            }
+
+            names.push(path);
+        }
+
+        assert_eq!(
+            names,
+            vec![
+                PathBuf::from("project"),
+                PathBuf::from("project/src"),
+                PathBuf::from("project/src/main.rs"),
+            ]
+        );
+
+        assert_eq!(contents, "fn main() {}");
+
+        assert!(
+            ArchiveOutput::create(&archive_path, "project").is_err(),
+            "replaced an archive that already exists"
+        );
+    }
+
+    #[test]
+    fn test_link_in_root() {
+        assert!(link_in_root(Path::new("link"), Path::new("src/main.rs")));
+        assert!(link_in_root(Path::new("a/b/link"), Path::new("../../c")));
+        assert!(link_in_root(Path::new("a/link"), Path::new("./b/../c")));
+
+        assert!(!link_in_root(Path::new("a/link"), Path::new("../../c")));
+        assert!(!link_in_root(Path::new("link"), Path::new("/etc/passwd")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_copy_symlinks() {
+        let test_data = test_utils::TestData::default();
+
+        let root = test_data.temp_path.join("project");
+
+        fs::create_dir_all(root.join("node_modules/.bin")).unwrap();
+        fs::write(root.join("node_modules/tool.js"), "").unwrap();
+        std::os::unix::fs::symlink(
+            "../tool.js",
+            root.join("node_modules/.bin/tool"),
+        )
+        .unwrap();
+
+        let archive_path = test_data.temp_path.join("project.tar.gz");
+
+        let mut output =
+            ArchiveOutput::create(&archive_path, "project").unwrap();
+
+        copy_to_output(&root, &mut output).expect("did not copy a symlink");
+        output.finish().unwrap();
+
+        let mut archive = tar::Archive::new(GzDecoder::new(
+            fs::File::open(&archive_path).unwrap(),
+        ));
+
+        let link = archive
+            .entries()
+            .unwrap()
+            .map(|entry| entry.unwrap())
+            .find(|entry| {
+                entry.path().unwrap()
+                    == Path::new("project/node_modules/.bin/tool")
+            })
+            .expect("did not write the symlink");
+
+        assert_eq!(link.header().entry_type(), tar::EntryType::Symlink);
+        assert_eq!(
+            link.link_name().unwrap().as_deref(),
+            Some(Path::new("../tool.js"))
+        );
+
+        std::os::unix::fs::symlink("../../..", root.join("out")).unwrap();
+
+        assert!(
+            copy_to_output(&root, &mut MemoryOutput::default()).is_err(),
+            "copied a symlink that points outside the project"
+        );
+    }
+
+    #[test]
+    fn test_archive_output_removed_on_drop() {
+        let test_data = test_utils::TestData::default();
+
+        let archive_path = test_data.temp_path.join("project.zip");
+
+        let output = ArchiveOutput::create(&archive_path, "project").unwrap();
+
+        drop(output);
+
+        assert!(!archive_path.exists(), "kept a half written archive");
+    }
+}
@@ -0,0 +1,1147 @@
+use std::{
+    collections::HashMap,
+    env,
+    error::Error,
+    fs,
+    io::{self, Read},
+    path::{Component, Path, PathBuf},
+};
+
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use toml_edit::{value, Array, Document, Item, Table};
+
+use crate::{
+    config::{
+        check_config, find_skeleton, load_main_config, parse_skel_config,
+        user_config_path, MainConfig, Skeleton,
+    },
+    error::SkelError,
+    output::{
+        copy_to_output, link_in_root, ArchiveFormat, ArchiveOutput, DirOutput,
+        Output, WorkDir,
+    },
+    report::Reporter,
+};
+
+// the name of the skeleton file in a packed skeleton
+const SKELETON_FILE: &str = "skeleton.toml";
+
+// the name of the manifest in a packed skeleton
+const MANIFEST_FILE: &str = "manifest.toml";
+
+/// what is in a packed skeleton, it is written to `manifest.toml` next to
+/// `skeleton.toml`
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Manifest {
+    pub name: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// the version of skel that packed it
+    pub skel_version: String,
+    /// every file in the pack relative to the pack root
+    #[serde(default)]
+    pub files: Vec<String>,
+}
+
+/// a skeleton that was packed
+#[derive(Debug)]
+pub struct Packed {
+    pub name: String,
+    pub archive: PathBuf,
+}
+
+/// a packed skeleton that was installed
+#[derive(Debug)]
+pub struct Installed {
+    pub name: String,
+    pub dir: PathBuf,
+    pub config_path: PathBuf,
+}
+
+// something a skeleton reads when it is made, `field` is where it is in the
+// skeleton like `templates[0].include`
+struct SkelRef {
+    field: String,
+    value: String,
+    // a build script or run step, the files it uses can't be known
+    script: bool,
+}
+
+// every file a skeleton reads and every script it runs, templated with
+// `skel_dir` as `skel-dir`
+fn skeleton_refs(
+    skel_path: &Path,
+    text: &str,
+    skel_dir: &Path,
+    config_dir: &Path,
+    name: &str,
+) -> Result<Vec<SkelRef>, Box<dyn Error>> {
+    let mut template_data = HashMap::from([
+        ("name".to_string(), name.to_string()),
+        ("root".to_string(), name.to_string()),
+        (
+            "skel-dir".to_string(),
+            skel_dir.to_string_lossy().to_string(),
+        ),
+        (
+            "config-dir".to_string(),
+            config_dir.to_string_lossy().to_string(),
+        ),
+    ]);
+
+    let skel_conf =
+        parse_skel_config(skel_path, text, &mut template_data, false)?;
+
+    let engine = skel_conf.engine.unwrap_or_default().engine();
+
+    let mut refs = vec![];
+
+    let mut add = |field: String, value: &str, script: bool| {
+        refs.push(SkelRef {
+            field,
+            value: value.to_string(),
+            script,
+        })
+    };
+
+    if let Some(ref build) = skel_conf.build {
+        add("build".into(), build, true);
+    }
+
+    for (i, template) in skel_conf.templates.iter().flatten().enumerate() {
+        if let Some(ref include) = template.include {
+            add(format!("templates[{}].include", i), include, false);
+        }
+    }
+
+    // steps are templated as they run, a variable captured by an earlier step
+    // is empty here
+    let source = skel_path.to_string_lossy();
+    let render = |text: &str| engine.render(&source, text, &template_data);
+
+    for (i, step) in skel_conf.steps.iter().flatten().enumerate() {
+        if let Some(include) =
+            step.template.as_ref().and_then(|t| t.include.as_ref())
+        {
+            add(
+                format!("steps[{}].template.include", i),
+                &render(include)?,
+                false,
+            );
+        }
+
+        // a copy is relative to the skeleton dir
+        if let Some(ref copy) = step.copy {
+            add(
+                format!("steps[{}].copy.from", i),
+                &skel_dir.join(render(&copy.from)?).to_string_lossy(),
+                false,
+            );
+        }
+
+        if let Some(ref run) = step.run {
+            add(format!("steps[{}].run", i), &render(run)?, true);
+        }
+    }
+
+    for (i, edit) in skel_conf.edits.iter().flatten().enumerate() {
+        if let Some(ref apply) = edit.apply {
+            add(format!("edits[{}].apply", i), apply, false);
+        }
+    }
+
+    for (i, merge) in skel_conf.merge.iter().flatten().enumerate() {
+        if let Some(ref include) = merge.include {
+            add(format!("merge[{}].include", i), include, false);
+        }
+    }
+
+    Ok(refs)
+}
+
+// the path a symlink at `link` points to, `link_in_root` has to be true
+fn link_path(link: &Path, target: &Path) -> PathBuf {
+    let mut path = link.parent().map(Path::to_path_buf).unwrap_or_default();
+
+    for component in target.components() {
+        match component {
+            Component::Normal(part) => path.push(part),
+            Component::ParentDir => {
+                path.pop();
+            }
+            _ => {}
+        }
+    }
+
+    path
+}
+
+// copy a file, dir or symlink at `relative` in the skeleton dir in to the pack
+//
+// a symlink is packed as a symlink with the path it points to, it has to point
+// to something in the skeleton dir. `staged` are the paths already in the pack
+fn stage_path(
+    skel_dir: &Path,
+    stage: &Path,
+    relative: &Path,
+    staged: &mut Vec<PathBuf>,
+) -> Result<(), Box<dyn Error>> {
+    // a dir that is already packed has everything in it packed
+    if staged.iter().any(|path| relative.starts_with(path)) {
+        return Ok(());
+    }
+
+    staged.push(relative.to_path_buf());
+
+    copy_path(skel_dir, stage, relative, staged)
+}
+
+fn copy_path(
+    skel_dir: &Path,
+    stage: &Path,
+    relative: &Path,
+    staged: &mut Vec<PathBuf>,
+) -> Result<(), Box<dyn Error>> {
+    let src = skel_dir.join(relative);
+    let dest = stage.join(relative);
+
+    let metadata =
+        fs::symlink_metadata(&src).map_err(|e| SkelError::io(&src, e))?;
+
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent).map_err(|e| SkelError::io(parent, e))?;
+    }
+
+    if metadata.file_type().is_symlink() {
+        let target = fs::read_link(&src).map_err(|e| SkelError::io(&src, e))?;
+
+        if !link_in_root(relative, &target) {
+            return Err(Box::from(format!(
+                "can't pack the symlink {}, it points outside {} to {}",
+                src.display(),
+                skel_dir.display(),
+                target.display()
+            )));
+        }
+
+        // a link in a dir that was packed on its own before
+        if fs::symlink_metadata(&dest).is_err() {
+            DirOutput::new(stage)
+                .create_symlink(relative, &target)
+                .map_err(|e| SkelError::io(&dest, e))?;
+        }
+
+        stage_path(skel_dir, stage, &link_path(relative, &target), staged)?;
+    } else if metadata.is_dir() {
+        fs::create_dir_all(&dest).map_err(|e| SkelError::io(&dest, e))?;
+
+        let mut entries = fs::read_dir(&src)
+            .map_err(|e| SkelError::io(&src, e))?
+            .map(|entry| entry.map(|entry| entry.file_name()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| SkelError::io(&src, e))?;
+
+        entries.sort();
+
+        for entry in entries {
+            copy_path(skel_dir, stage, &relative.join(entry), staged)?;
+        }
+    } else {
+        fs::copy(&src, &dest).map_err(|e| SkelError::io(&src, e))?;
+    }
+
+    Ok(())
+}
+
+// copy a skeleton and everything it uses from its dir in to `stage`
+//
+// a skeleton that is a `skeleton.toml` is packed with its whole dir, any other
+// skeleton file is packed as `skeleton.toml` with the files it reads. the
+// skeleton is templated with its own dir and again with `stage` as `skel-dir`,
+// a file that does not move with `skel-dir` can't be packed
+fn stage_skeleton(
+    skel_path: &Path,
+    config_dir: &Path,
+    name: &str,
+    stage: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let text = fs::read_to_string(skel_path)
+        .map_err(|e| SkelError::io(skel_path, e))?;
+
+    let skel_dir = skel_path.parent().unwrap_or_else(|| Path::new("."));
+
+    let whole_dir = skel_path.file_name() == Some(SKELETON_FILE.as_ref());
+
+    let refs = skeleton_refs(skel_path, &text, skel_dir, config_dir, name)?;
+    let moved = skeleton_refs(skel_path, &text, stage, config_dir, name)?;
+
+    let mut files = vec![];
+
+    for (skel_ref, moved) in refs.iter().zip(moved.iter()) {
+        if skel_ref.script {
+            if skel_ref.value != moved.value && !whole_dir {
+                return Err(Box::from(format!(
+                    "{} can't be packed, {} uses {{{{skel-dir}}}} so the files \
+                    it needs are not known, name the skeleton {} to pack its \
+                    whole dir",
+                    skel_path.display(),
+                    skel_ref.field,
+                    SKELETON_FILE
+                )));
+            }
+
+            continue;
+        }
+
+        let relative = Path::new(&skel_ref.value).strip_prefix(skel_dir).ok();
+
+        let packable = match relative {
+            Some(relative) => {
+                Path::new(&moved.value).strip_prefix(stage).ok()
+                    == Some(relative)
+                    && relative.components().all(|c| {
+                        matches!(c, Component::Normal(_) | Component::CurDir)
+                    })
+            }
+            None => false,
+        };
+
+        let relative = match relative {
+            Some(relative) if packable => relative,
+            _ => {
+                let hint = if Path::new(&skel_ref.value).is_relative() {
+                    ", a relative path is read from the current dir"
+                } else {
+                    ""
+                };
+
+                return Err(Box::from(format!(
+                    "{} can't be packed, {} reads {}, only paths under \
+                    {{{{skel-dir}}}} can be packed{}",
+                    skel_path.display(),
+                    skel_ref.field,
+                    skel_ref.value,
+                    hint
+                )));
+            }
+        };
+
+        let src = skel_dir.join(relative);
+
+        if !src.exists() {
+            return Err(Box::from(format!(
+                "{} can't be packed, {} reads {} but it is not there",
+                skel_path.display(),
+                skel_ref.field,
+                src.display()
+            )));
+        }
+
+        files.push(relative.to_path_buf());
+    }
+
+    if whole_dir {
+        files = vec![PathBuf::new()];
+    }
+
+    let mut staged = vec![];
+
+    // a dir is packed before the files in it
+    files.sort();
+
+    for relative in files {
+        stage_path(skel_dir, stage, &relative, &mut staged)?;
+    }
+
+    if whole_dir {
+        return Ok(());
+    }
+
+    fs::write(stage.join(SKELETON_FILE), text)
+        .map_err(|e| SkelError::io(skel_path, e))?;
+
+    Ok(())
+}
+
+/// pack a skeleton from the main configs in to a `.tar.gz` with a manifest,
+/// the archive is `<name>.tar.gz` when it is not given
+///
+/// everything in the archive is in a dir named after the skeleton
+pub fn pack_skeleton(
+    alt_config_path: Option<&Path>,
+    target: &str,
+    archive: Option<&Path>,
+    reporter: &Reporter,
+) -> Result<Packed, SkelError> {
+    let (main_config, _) = load_main_config(alt_config_path, reporter)?;
+
+    let (name, skeleton) = find_skeleton(target, &main_config)?;
+
+    let name = name.clone();
+
+    let archive = archive
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from(format!("{}.tar.gz", name)));
+
+    if ArchiveFormat::from_path(&archive) != Some(ArchiveFormat::TarGz) {
+        return Err(SkelError::Other(format!(
+            "can only pack a skeleton in to a .tar.gz or .tgz not {}",
+            archive.display()
+        )));
+    }
+
+    let skel_path = PathBuf::from(&skeleton.path);
+
+    if !skel_path.is_file() {
+        return Err(SkelError::SkeletonNotFound {
+            name,
+            path: Some(skel_path),
+            suggestions: vec![],
+        });
+    }
+
+    let work_dir = WorkDir::new()?;
+
+    let config_dir = skeleton.source.parent().unwrap_or_else(|| Path::new(""));
+
+    stage_skeleton(&skel_path, config_dir, &name, &work_dir.path)?;
+
+    let meta = skeleton.meta();
+
+    let mut output = ArchiveOutput::create(&archive, &name)?;
+
+    let copied = copy_to_output(&work_dir.path, &mut output)?;
+
+    let manifest = Manifest {
+        name: name.clone(),
+        aliases: skeleton.aliases.clone(),
+        description: meta.description,
+        tags: meta.tags.unwrap_or_default(),
+        skel_version: env!("CARGO_PKG_VERSION").to_string(),
+        files: copied
+            .iter()
+            .filter(|path| {
+                fs::symlink_metadata(work_dir.path.join(path))
+                    .is_ok_and(|metadata| !metadata.is_dir())
+            })
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect(),
+    };
+
+    let manifest_text = toml::to_string(&manifest)
+        .map_err(|e| SkelError::Other(e.to_string()))?;
+
+    let io_err = |e| SkelError::io(&archive, e);
+
+    output
+        .write_file(Path::new(MANIFEST_FILE), manifest_text.as_bytes(), 0o644)
+        .map_err(io_err)?;
+
+    output.finish().map_err(io_err)?;
+
+    Ok(Packed { name, archive })
+}
+
+// the dir with the manifest in it, the manifest is in the top dir of a packed
+// skeleton
+fn find_pack_root(dir: &Path) -> Option<PathBuf> {
+    if dir.join(MANIFEST_FILE).is_file() {
+        return Some(dir.to_path_buf());
+    }
+
+    let mut dirs: Vec<PathBuf> = fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_dir())
+        .collect();
+
+    match dirs.pop() {
+        Some(dir) if dirs.is_empty() && dir.join(MANIFEST_FILE).is_file() => {
+            Some(dir)
+        }
+        _ => None,
+    }
+}
+
+// unpack a `.tar.gz` in to `dir`
+fn unpack(archive: &Path, dir: &Path) -> Result<(), Box<dyn Error>> {
+    let file =
+        fs::File::open(archive).map_err(|e| SkelError::io(archive, e))?;
+
+    tar::Archive::new(GzDecoder::new(file))
+        .unpack(dir)
+        .map_err(|e| SkelError::io(archive, e))?;
+
+    Ok(())
+}
+
+fn read_manifest(path: &Path) -> Result<Manifest, Box<dyn Error>> {
+    let mut text = String::new();
+
+    fs::File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut text))
+        .map_err(|e| SkelError::io(path, e))?;
+
+    let manifest: Manifest =
+        toml::from_str(&text).map_err(|e| SkelError::parse(path, &text, &e))?;
+
+    let bad_name = manifest.name.is_empty()
+        || manifest.name == "."
+        || manifest.name == ".."
+        || manifest.name.contains(['/', '\\']);
+
+    if bad_name {
+        return Err(Box::from(format!(
+            "{} has a bad skeleton name {:?}",
+            path.display(),
+            manifest.name
+        )));
+    }
+
+    Ok(manifest)
+}
+
+// check that the new skeleton's name and aliases do not find a skeleton that
+// is already in the main configs
+fn check_collisions(
+    mut main_config: MainConfig,
+    manifest: &Manifest,
+    config_path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    if let Some(old) = main_config.skeletons.get(&manifest.name) {
+        return Err(Box::from(format!(
+            "can not install {}, there is already a skeleton named {} in {}",
+            manifest.name,
+            manifest.name,
+            old.source.display()
+        )));
+    }
+
+    main_config.skeletons.insert(
+        manifest.name.clone(),
+        Skeleton {
+            aliases: manifest.aliases.clone(),
+            source: config_path.to_path_buf(),
+            ..Default::default()
+        },
+    );
+
+    check_config(&main_config).map_err(|e| {
+        Box::from(format!("can not install {}\n{}", manifest.name, e))
+    })
+}
+
+// add the skeleton to the main config, the config is made if it is not there
+fn register(
+    config_path: &Path,
+    manifest: &Manifest,
+) -> Result<(), Box<dyn Error>> {
+    let text = match fs::read_to_string(config_path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(Box::new(SkelError::io(config_path, e))),
+    };
+
+    let mut document: Document = text.parse().map_err(|e| {
+        format!("could not parse {} {}", config_path.display(), e)
+    })?;
+
+    let skeletons = document.entry("skeletons").or_insert_with(|| {
+        let mut table = Table::new();
+
+        table.set_implicit(true);
+
+        Item::Table(table)
+    });
+
+    let skeletons = skeletons.as_table_mut().ok_or_else(|| {
+        format!("skeletons in {} is not a table", config_path.display())
+    })?;
+
+    let mut table = Table::new();
+
+    table["path"] = value(format!(
+        "{{{{config-dir}}}}/skeletons/{}/{}",
+        manifest.name, SKELETON_FILE
+    ));
+
+    if !manifest.aliases.is_empty() {
+        table["aliases"] = value(manifest.aliases.iter().collect::<Array>());
+    }
+
+    if let Some(ref description) = manifest.description {
+        table["description"] = value(description);
+    }
+
+    if !manifest.tags.is_empty() {
+        table["tags"] = value(manifest.tags.iter().collect::<Array>());
+    }
+
+    skeletons.insert(&manifest.name, Item::Table(table));
+
+    if let Some(parent) = config_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| SkelError::io(parent, e))?;
+    }
+
+    fs::write(config_path, document.to_string())
+        .map_err(|e| SkelError::io(config_path, e))?;
+
+    Ok(())
+}
+
+/// install a packed skeleton from a `.tar.gz` or an unpacked dir in to
+/// `skeletons/<name>` next to the main config and add it to the config
+///
+/// the main config is the alt config when it is given and the user config if
+/// not. nothing is installed when the name or an alias is already used
+pub fn install_skeleton(
+    alt_config_path: Option<&Path>,
+    from: &Path,
+    reporter: &Reporter,
+) -> Result<Installed, SkelError> {
+    let work_dir = WorkDir::new()?;
+
+    let unpacked = if from.is_dir() {
+        from.to_path_buf()
+    } else if from.is_file() {
+        if ArchiveFormat::from_path(from) != Some(ArchiveFormat::TarGz) {
+            return Err(SkelError::Other(format!(
+                "can only install a .tar.gz, .tgz or a dir not {}",
+                from.display()
+            )));
+        }
+
+        unpack(from, &work_dir.path)?;
+
+        work_dir.path.clone()
+    } else {
+        return Err(SkelError::io(
+            from,
+            io::Error::from(io::ErrorKind::NotFound),
+        ));
+    };
+
+    let root = find_pack_root(&unpacked).ok_or_else(|| {
+        format!(
+            "{} is not a packed skeleton, it has no manifest",
+            from.display()
+        )
+    })?;
+
+    let manifest = read_manifest(&root.join(MANIFEST_FILE))?;
+
+    if !root.join(SKELETON_FILE).is_file() {
+        return Err(SkelError::Other(format!(
+            "{} is not a packed skeleton, it has no {}",
+            from.display(),
+            SKELETON_FILE
+        )));
+    }
+
+    let config_path = match alt_config_path {
+        Some(path) => path.to_path_buf(),
+        None => user_config_path(&|key| env::var_os(key)).ok_or(
+            "can not find the user config, set XDG_CONFIG_HOME or HOME",
+        )?,
+    };
+
+    // there is nothing to collide with when there is no main config yet
+    let main_config = match load_main_config(alt_config_path, reporter) {
+        Ok((main_config, _)) => main_config,
+        Err(SkelError::ConfigNotFound { .. }) => MainConfig::default(),
+        Err(e) => return Err(e),
+    };
+
+    check_collisions(main_config, &manifest, &config_path)?;
+
+    let config_dir = config_path.parent().unwrap_or_else(|| Path::new("."));
+    let dir = config_dir.join("skeletons").join(&manifest.name);
+
+    if dir.exists() {
+        return Err(SkelError::Other(format!(
+            "can not install {}, {} is already there",
+            manifest.name,
+            dir.display()
+        )));
+    }
+
+    copy_to_output(&root, &mut DirOutput::new(&dir))?;
+
+    if let Err(e) = register(&config_path, &manifest) {
+        let _ = fs::remove_dir_all(&dir);
+
+        return Err(e.into());
+    }
+
+    Ok(Installed {
+        name: manifest.name,
+        dir,
+        config_path,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::{report::Verbosity, test_utils};
+
+    // a main config with a skeleton that uses a file in its dir
+    fn make_config(temp: &Path) -> PathBuf {
+        let config_path = temp.join("skel/config.toml");
+        let projects = temp.join("skel/projects");
+
+        fs::create_dir_all(projects.join("templates")).unwrap();
+
+        fs::write(
+            &config_path,
+            "[skeletons]\n\
+            cli.path = \"{{config-dir}}/projects/cli.toml\"\n\
+            cli.aliases = [\"c\"]\n\
+            cli.description = \"a cli\"\n",
+        )
+        .unwrap();
+
+        fs::write(
+            projects.join("cli.toml"),
+            "[[templates]]\n\
+            path = \"main.rs\"\n\
+            include = \"{{skel-dir}}/templates/main.rs\"\n",
+        )
+        .unwrap();
+
+        fs::write(projects.join("templates/main.rs"), "fn main() {}").unwrap();
+
+        // not used by the skeleton so it is not packed
+        fs::write(projects.join("other.toml"), "dirs = []").unwrap();
+
+        config_path
+    }
+
+    // a skeleton dir with some files in it and the skeleton `cli.toml`
+    fn make_skel_dir(temp: &Path) -> PathBuf {
+        let skel_dir = temp.join("projects");
+
+        fs::create_dir_all(skel_dir.join("files/nested")).unwrap();
+        fs::write(skel_dir.join("files/nested/a.txt"), "a").unwrap();
+        fs::write(skel_dir.join("deps.toml"), "[dependencies]").unwrap();
+        fs::write(skel_dir.join("main.patch"), "").unwrap();
+        fs::write(skel_dir.join("main.rs"), "fn main() {}").unwrap();
+        fs::write(skel_dir.join("cli.rs"), "").unwrap();
+        fs::write(skel_dir.join("unused.txt"), "").unwrap();
+
+        skel_dir
+    }
+
+    // stage `cli.toml` in `skel_dir` with `text` in it
+    fn stage_text(skel_dir: &Path, text: &str) -> Result<WorkDir, String> {
+        let skel_path = skel_dir.join("cli.toml");
+
+        fs::write(&skel_path, text).unwrap();
+
+        let stage = WorkDir::new().unwrap();
+
+        let config_dir = skel_dir.parent().unwrap();
+
+        stage_skeleton(&skel_path, config_dir, "cli", &stage.path)
+            .map(|_| stage)
+            .map_err(|e| e.to_string())
+    }
+
+    // stage `text` and check only `packed` are packed with the skeleton
+    fn assert_packs(text: &str, packed: &[&str]) {
+        let test_data = test_utils::TestData::default();
+        let skel_dir = make_skel_dir(&test_data.temp_path);
+
+        let stage = stage_text(&skel_dir, text)
+            .unwrap_or_else(|e| panic!("did not pack {:?} {}", packed, e));
+
+        for file in packed.iter().chain(&["skeleton.toml"]) {
+            assert!(stage.path.join(file).is_file(), "did not pack {}", file);
+        }
+
+        assert!(
+            !stage.path.join("unused.txt").exists(),
+            "packed a file the skeleton does not read"
+        );
+    }
+
+    #[test]
+    fn test_stage_template_include() {
+        assert_packs(
+            "[[templates]]
+path = \"main.rs\"
+\
+            include = \"{{skel-dir}}/main.rs\"
+",
+            &["main.rs"],
+        );
+    }
+
+    #[test]
+    fn test_stage_step_template_include() {
+        assert_packs(
+            "[[steps]]
+template = { path = \"{{name}}.rs\", \
+            include = \"{{skel-dir}}/{{name}}.rs\" }
+\
+            [[steps]]
+template = { path = \"main.rs\", \
+            include = \"{{skel-dir}}/main.rs\" }
+",
+            &["cli.rs", "main.rs"],
+        );
+    }
+
+    #[test]
+    fn test_stage_copy_from() {
+        assert_packs(
+            "[[steps]]
+copy = { from = \"files\", to = \"files\" }
+",
+            &["files/nested/a.txt"],
+        );
+    }
+
+    #[test]
+    fn test_stage_merge_include() {
+        assert_packs(
+            "[[merge]]
+path = \"Cargo.toml\"
+\
+            include = \"{{skel-dir}}/deps.toml\"
+",
+            &["deps.toml"],
+        );
+    }
+
+    #[test]
+    fn test_stage_edits_apply() {
+        assert_packs(
+            "[[edits]]
+path = \"src/main.rs\"
+\
+            apply = \"{{skel-dir}}/main.patch\"
+",
+            &["main.patch"],
+        );
+    }
+
+    #[test]
+    fn test_stage_not_packable() {
+        let test_data = test_utils::TestData::default();
+        let skel_dir = make_skel_dir(&test_data.temp_path);
+
+        let not_packable = [
+            // read from the current dir
+            (
+                "[[templates]]
+path = \"a\"
+include = \"deps.toml\"
+",
+                "templates[0].include",
+            ),
+            (
+                "[[merge]]
+path = \"a\"
+\
+                include = \"{{config-dir}}/projects/deps.toml\"
+",
+                "merge[0].include",
+            ),
+            (
+                "[[edits]]
+path = \"a\"
+apply = \"/etc/hosts\"
+",
+                "edits[0].apply",
+            ),
+            (
+                "[[steps]]
+copy = { from = \"../outside\", to = \"a\" }
+",
+                "steps[0].copy.from",
+            ),
+            (
+                "[[steps]]
+template = { path = \"a\", \
+                include = \"{{skel-dir}}/missing.txt\" }
+",
+                "steps[0].template.include",
+            ),
+            (
+                "build = \"sh {{skel-dir}}/setup.sh\"
+",
+                "build",
+            ),
+        ];
+
+        for (text, field) in not_packable {
+            let err = stage_text(&skel_dir, text)
+                .err()
+                .unwrap_or_else(|| panic!("some how packed {}", field));
+
+            assert!(err.contains(field), "did not name {} in {}", field, err);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_stage_symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let test_data = test_utils::TestData::default();
+        let skel_dir = make_skel_dir(&test_data.temp_path);
+
+        symlink("nested/a.txt", skel_dir.join("files/a.txt")).unwrap();
+        symlink("deps.toml", skel_dir.join("deps-link.toml")).unwrap();
+
+        // a link the skeleton reads is packed with what it points to
+        let stage = stage_text(
+            &skel_dir,
+            "[[merge]]
+path = \"Cargo.toml\"
+\
+            include = \"{{skel-dir}}/deps-link.toml\"
+",
+        )
+        .expect("did not pack a symlink");
+
+        assert_eq!(
+            fs::read_link(stage.path.join("deps-link.toml")).unwrap(),
+            Path::new("deps.toml"),
+            "did not pack the symlink as a symlink"
+        );
+        assert!(stage.path.join("deps.toml").is_file());
+
+        // a link in a copied dir
+        let stage = stage_text(
+            &skel_dir,
+            "[[steps]]
+copy = { from = \"files\", to = \"files\" }
+",
+        )
+        .expect("did not pack a dir with a symlink");
+
+        assert_eq!(
+            fs::read_link(stage.path.join("files/a.txt")).unwrap(),
+            Path::new("nested/a.txt")
+        );
+
+        // a link to a parent dir is packed once
+        symlink("..", skel_dir.join("files/up")).unwrap();
+
+        let stage = stage_text(
+            &skel_dir,
+            "[[steps]]
+copy = { from = \"files\", to = \"files\" }
+",
+        )
+        .expect("did not pack a link to a parent dir");
+
+        assert!(stage.path.join("deps.toml").is_file());
+
+        fs::remove_file(skel_dir.join("files/up")).unwrap();
+
+        symlink("../../outside.txt", skel_dir.join("files/out.txt")).unwrap();
+
+        let err = stage_text(
+            &skel_dir,
+            "[[steps]]
+copy = { from = \"files\", to = \"files\" }
+",
+        )
+        .err()
+        .expect("some how packed a link outside the skeleton dir");
+
+        assert!(err.contains("outside"), "did not say why {}", err);
+    }
+
+    #[test]
+    fn test_pack_and_install() {
+        let test_data = test_utils::TestData::default();
+        let temp = &test_data.temp_path;
+
+        let reporter = Reporter::new(Verbosity::Quiet, None);
+
+        let config_path = make_config(temp);
+        let archive = temp.join("cli.tar.gz");
+
+        pack_skeleton(Some(&config_path), "c", Some(&archive), &reporter)
+            .expect("did not pack the skeleton");
+
+        // the same archive is not written over
+        assert!(
+            pack_skeleton(Some(&config_path), "cli", Some(&archive), &reporter)
+                .is_err(),
+            "some how packed over an archive"
+        );
+
+        let unpacked = temp.join("unpacked");
+
+        fs::create_dir(&unpacked).unwrap();
+        unpack(&archive, &unpacked).unwrap();
+
+        let manifest = read_manifest(&unpacked.join("cli/manifest.toml"))
+            .expect("did not write a manifest");
+
+        assert_eq!(manifest.name, "cli");
+        assert_eq!(manifest.aliases, ["c"]);
+        assert_eq!(manifest.description.as_deref(), Some("a cli"));
+        assert_eq!(manifest.files, ["skeleton.toml", "templates/main.rs"]);
+
+        assert!(
+            !unpacked.join("cli/other.toml").exists(),
+            "packed a file the skeleton does not use"
+        );
+
+        // the name is already in the config it was packed from
+        let err = install_skeleton(Some(&config_path), &archive, &reporter)
+            .expect_err("some how installed over a skeleton");
+
+        assert!(err.to_string().contains("already a skeleton named cli"));
+
+        let other_config = temp.join("other/config.toml");
+
+        fs::create_dir_all(temp.join("other")).unwrap();
+        fs::write(&other_config, "ignore_case = true\n").unwrap();
+
+        let installed =
+            install_skeleton(Some(&other_config), &archive, &reporter)
+                .expect("did not install the skeleton");
+
+        assert_eq!(installed.dir, temp.join("other/skeletons/cli"));
+        assert!(installed.dir.join("templates/main.rs").is_file());
+
+        let (main_config, _) = load_main_config(Some(&other_config), &reporter)
+            .expect("did not add the skeleton to the config");
+
+        let (name, skeleton) = find_skeleton("c", &main_config).unwrap();
+
+        assert_eq!(name, "cli", "did not find the key of the alias");
+        assert_eq!(
+            PathBuf::from(&skeleton.path),
+            installed.dir.join("skeleton.toml")
+        );
+        assert!(
+            fs::read_to_string(&other_config)
+                .unwrap()
+                .starts_with("ignore_case = true\n"),
+            "did not keep the config"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_pack_symlinks() {
+        let test_data = test_utils::TestData::default();
+        let temp = &test_data.temp_path;
+
+        let reporter = Reporter::new(Verbosity::Quiet, None);
+
+        let config_path = make_config(temp);
+        let archive = temp.join("cli.tar.gz");
+
+        let projects = temp.join("skel/projects");
+
+        std::os::unix::fs::symlink(
+            "main.rs",
+            projects.join("templates/lib.rs"),
+        )
+        .unwrap();
+
+        fs::write(
+            projects.join("cli.toml"),
+            "[[templates]]\n\
+            path = \"lib.rs\"\n\
+            include = \"{{skel-dir}}/templates/lib.rs\"\n",
+        )
+        .unwrap();
+
+        pack_skeleton(Some(&config_path), "cli", Some(&archive), &reporter)
+            .expect("did not pack a skeleton with a symlink");
+
+        let file = fs::File::open(&archive).unwrap();
+        let mut tar = tar::Archive::new(GzDecoder::new(file));
+
+        let link = tar
+            .entries()
+            .unwrap()
+            .map(Result::unwrap)
+            .find(|entry| {
+                entry.path().unwrap() == Path::new("cli/templates/lib.rs")
+            })
+            .expect("did not pack the symlink");
+
+        assert_eq!(link.header().entry_type(), tar::EntryType::Symlink);
+        assert_eq!(
+            link.link_name().unwrap().as_deref(),
+            Some(Path::new("main.rs"))
+        );
+
+        let other_config = temp.join("other/config.toml");
+
+        let installed =
+            install_skeleton(Some(&other_config), &archive, &reporter)
+                .expect("did not install a skeleton with a symlink");
+
+        assert_eq!(
+            fs::read_link(installed.dir.join("templates/lib.rs")).unwrap(),
+            Path::new("main.rs")
+        );
+        assert!(installed.dir.join("templates/main.rs").is_file());
+
+        let manifest =
+            read_manifest(&installed.dir.join(MANIFEST_FILE)).unwrap();
+
+        assert_eq!(
+            manifest.files,
+            ["skeleton.toml", "templates/lib.rs", "templates/main.rs"]
+        );
+    }
+
+    #[test]
+    fn test_install_collisions() {
+        let test_data = test_utils::TestData::default();
+        let temp = &test_data.temp_path;
+
+        let reporter = Reporter::new(Verbosity::Quiet, None);
+
+        let config_path = make_config(temp);
+        let archive = temp.join("cli.tar.gz");
+
+        pack_skeleton(Some(&config_path), "cli", Some(&archive), &reporter)
+            .unwrap();
+
+        let other_config = temp.join("other/config.toml");
+
+        fs::create_dir_all(temp.join("other")).unwrap();
+        fs::write(
+            &other_config,
+            "ignore_case = true\n[skeletons]\nrust.path = \"rust.toml\"\n\
+            rust.aliases = [\"C\"]\n",
+        )
+        .unwrap();
+
+        let before = fs::read_to_string(&other_config).unwrap();
+
+        let err = install_skeleton(Some(&other_config), &archive, &reporter)
+            .expect_err("some how installed a skeleton with a used alias");
+
+        assert!(err.to_string().contains("can not install cli"));
+        assert_eq!(
+            fs::read_to_string(&other_config).unwrap(),
+            before,
+            "changed the config after a collision"
+        );
+        assert!(
+            !temp.join("other/skeletons").exists(),
+            "copied the skeleton after a collision"
+        );
+    }
+}
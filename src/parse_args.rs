@@ -1,26 +1,296 @@
-use std::error::Error;
+use std::{
+    env,
+    error::Error,
+    ffi::OsString,
+    io::{self, IsTerminal},
+    path::{Path, PathBuf},
+};
 
-use clap::Parser;
+use clap::{builder::StyledStr, Parser, Subcommand};
+use clap_complete::{
+    ArgValueCandidates, ArgValueCompleter, CompletionCandidate, PathCompleter,
+};
+
+use crate::{
+    config::{find_skeleton, load_main_config, skeleton_vars},
+    report::{Reporter, Verbosity},
+    style::ColorChoice,
+};
+
+/// the shells `skel completions` can make a script for
+pub const COMPLETION_SHELLS: [&str; 5] =
+    ["bash", "elvish", "fish", "powershell", "zsh"];
+
+// the alt config path in the args being completed, the completions read the
+// same configs as making a project does
+fn completing_alt_config_path() -> Option<OsString> {
+    let mut args = env::args_os().skip_while(|arg| arg != "--").skip(1);
+
+    while let Some(arg) = args.next() {
+        let text = arg.to_string_lossy();
+
+        if text == "-a" || text == "--alt-config-path" {
+            return args.next();
+        }
+
+        if let Some(path) = text.strip_prefix("--alt-config-path=") {
+            return Some(path.into());
+        }
+
+        if let Some(path) = text.strip_prefix("-a").filter(|p| !p.is_empty()) {
+            return Some(path.into());
+        }
+    }
+
+    None
+}
+
+// every skeleton name and alias with the skeleton description
+fn skeleton_candidates() -> Vec<CompletionCandidate> {
+    let alt_config_path = completing_alt_config_path();
+
+    // a warning would be printed in the middle of the command line
+    let reporter = Reporter::new(Verbosity::Quiet, None);
+
+    let main_config = match load_main_config(
+        alt_config_path.as_deref().map(Path::new),
+        &reporter,
+    ) {
+        Ok((main_config, _)) => main_config,
+        Err(_) => return vec![],
+    };
+
+    let mut candidates = vec![];
+
+    for (name, skeleton) in main_config.skeletons.iter() {
+        let help = skeleton.meta().description.map(StyledStr::from);
+
+        candidates.push(CompletionCandidate::new(name).help(help.clone()));
+
+        for alias in skeleton.aliases.iter() {
+            candidates.push(CompletionCandidate::new(alias).help(help.clone()));
+        }
+    }
+
+    candidates
+}
+
+// a `--var` given as `KEY=VALUE`, the value can have more `=` in it
+fn parse_var(var: &str) -> Result<(String, String), String> {
+    match var.split_once('=') {
+        Some((key, value)) if !key.is_empty() => {
+            Ok((key.to_string(), value.to_string()))
+        }
+        Some(_) => Err(format!("{} has no variable name before the =", var)),
+        None => Err(format!("{} is not KEY=VALUE", var)),
+    }
+}
+
+// the skeleton file of the args being completed, the `-s` file or the skeleton
+// found in the main configs
+fn completing_skeleton_path() -> Option<PathBuf> {
+    let mut args: Vec<OsString> = env::args_os()
+        .skip_while(|arg| arg != "--")
+        .skip(1)
+        .collect();
+
+    // the word being completed and the option it is for are not finished
+    args.pop();
+
+    if args.last().is_some_and(|arg| arg == "--var") {
+        args.pop();
+    }
+
+    let skel_args = SkelArgs::try_parse_from(args).ok()?;
+
+    match skel_args.skeleton_file.as_deref() {
+        Some("-") => return None,
+        Some(skeleton_file) => return Some(PathBuf::from(skeleton_file)),
+        None => {}
+    }
+
+    let reporter = Reporter::new(Verbosity::Quiet, None);
+
+    let (main_config, _) = load_main_config(
+        skel_args.alt_config_path.as_deref().map(Path::new),
+        &reporter,
+    )
+    .ok()?;
+
+    let (_, skeleton) =
+        find_skeleton(skel_args.skeleton.as_deref()?, &main_config).ok()?;
+
+    Some(PathBuf::from(&skeleton.path))
+}
+
+// the variables the skeleton being made declares as `key=`
+fn var_candidates() -> Vec<CompletionCandidate> {
+    let vars = completing_skeleton_path()
+        .and_then(|path| skeleton_vars(&path, None).ok())
+        .unwrap_or_default();
+
+    vars.into_iter()
+        .map(|var| {
+            CompletionCandidate::new(format!("{}=", var.key))
+                .help(Some(StyledStr::from(var.template)))
+        })
+        .collect()
+}
+
+/// every command name, a skeleton can't be named one of these since the
+/// command would be run instead of making the skeleton
+pub const COMMAND_NAMES: [&str; 7] = [
+    "config",
+    "sources",
+    "list",
+    "pack",
+    "install",
+    "completions",
+    "help",
+];
+
+/// the commands that are not making a project
+#[derive(Subcommand, Debug)]
+pub enum SkelCommand {
+    /// look at the main configs
+    Config {
+        #[clap(subcommand)]
+        command: ConfigCommand,
+    },
+    /// manage the git sources in the main configs
+    Sources {
+        #[clap(subcommand)]
+        command: SourcesCommand,
+    },
+    /// list the skeletons with their aliases and descriptions
+    List {
+        #[clap(long)]
+        /// only list the skeletons with this tag
+        tag: Option<String>,
+    },
+    /// pack a skeleton and the files it uses in to a .tar.gz to share
+    Pack {
+        #[clap(add = ArgValueCandidates::new(skeleton_candidates))]
+        /// the skeleton to pack, can be the skeleton name or alias
+        skeleton: String,
+        #[clap(add = ArgValueCompleter::new(PathCompleter::file()))]
+        /// the archive to write, `<name>.tar.gz` by default
+        archive: Option<String>,
+    },
+    /// install a packed skeleton in to the user config or the alt config
+    Install {
+        #[clap(add = ArgValueCompleter::new(PathCompleter::any()))]
+        /// a .tar.gz made by `skel pack` or the dir it was unpacked in to
+        path: String,
+    },
+    /// print the completion script for a shell
+    Completions {
+        #[clap(value_parser = COMPLETION_SHELLS)]
+        shell: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// show which config each skeleton is in and which skeletons are shadowed
+    Sources,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SourcesCommand {
+    /// fetch every git source again and lock it to the newest commit of its
+    /// rev
+    Update,
+}
 
 /// make a project from a skeleton defined in a toml file
 #[derive(Parser, Default, Debug)]
+#[command(args_conflicts_with_subcommands = true)]
 pub struct SkelArgs {
+    #[clap(subcommand)]
+    pub command: Option<SkelCommand>,
+    #[clap(add = ArgValueCandidates::new(skeleton_candidates))]
     /// the skeleton to make, can be the skeleton name or alias
     pub skeleton: Option<String>,
-    /// the name of the new project to make
-    pub name: Option<String>,
+    /// the dir to make the project in, `.` for the current dir, the last part
+    /// is the project name
+    pub target: Option<String>,
     #[clap(short, long)]
-    /// a path to a skeleton file
+    /// the project name when it is not the last part of the target
+    pub name: Option<String>,
+    #[clap(short, long, add = ArgValueCompleter::new(PathCompleter::file().stdio()))]
+    /// a path to a skeleton file, `-` to read it from stdin, does not need a
+    /// main config
     pub skeleton_file: Option<String>,
-    #[clap(short, long)]
+    #[clap(short, long, global = true, add = ArgValueCompleter::new(PathCompleter::file()))]
     /// a path to a main config file
     pub alt_config_path: Option<String>,
     #[clap(short = 'D', long)]
     /// a different root to make the project in to
     pub different_root: Option<String>,
     #[clap(short, long)]
+    /// make the different root and any missing parent dirs
+    pub parents: bool,
+    #[clap(short, long)]
     /// print out what will be done
     pub dry_run: bool,
+    #[clap(short, long, value_name = "ARCHIVE")]
+    /// write the project to a .tar.gz, .tgz or .zip archive instead of a dir
+    pub output: Option<String>,
+    #[clap(short, long, global = true, action = clap::ArgAction::Count)]
+    /// print each dir, file and template as it is made and when scripts start
+    /// and finish, give twice to also print script stderr
+    pub verbose: u8,
+    #[clap(short, long, global = true, conflicts_with = "verbose")]
+    /// only print errors
+    pub quiet: bool,
+    #[clap(
+        long,
+        global = true,
+        value_enum,
+        default_value_t,
+        value_name = "WHEN"
+    )]
+    /// when to use color, honors NO_COLOR and CLICOLOR_FORCE with auto
+    pub color: ColorChoice,
+    #[clap(
+        long = "var",
+        value_name = "KEY=VALUE",
+        value_parser = parse_var,
+        add = ArgValueCandidates::new(var_candidates)
+    )]
+    /// a template variable, wins over the skeleton's derived variables and can
+    /// be given more than once
+    pub vars: Vec<(String, String)>,
+    #[clap(long)]
+    /// write the build output with timestamps to a file, a relative path is
+    /// put in the project
+    pub log_file: Option<String>,
+    #[clap(long)]
+    /// do not run the build script or run steps
+    pub no_build: bool,
+    #[clap(long, conflicts_with = "no_build")]
+    /// run the build script and run steps without asking to trust them
+    pub trust: bool,
+    #[clap(long)]
+    /// run scripts with an empty environment
+    pub clear_env: bool,
+    #[clap(long, value_name = "VAR")]
+    /// an environment variable to keep with --clear-env, can be given more
+    /// than once
+    pub env_allow: Vec<String>,
+    #[clap(long, value_name = "SECONDS")]
+    /// kill scripts that run longer than this and fail
+    pub timeout: Option<u64>,
+    #[clap(long)]
+    /// the umask to run scripts with, e.g. 022
+    pub umask: Option<String>,
+    #[clap(long, value_name = "PROFILE")]
+    /// a resource limit profile for scripts, none, standard or strict
+    pub limits: Option<String>,
+    #[clap(skip)]
+    /// nothing to make was given on a terminal so a skeleton is picked
+    pub pick: bool,
 }
 
 // TODO: make a better error messages
@@ -28,42 +298,131 @@ pub struct SkelArgs {
 pub fn parse_args() -> Result<SkelArgs, Box<dyn Error>> {
     let mut skel_args = SkelArgs::parse();
 
+    if skel_args.command.is_some() {
+        return Ok(skel_args);
+    }
+
+    // any option before a command makes it the skeleton to make instead
+    if let Some(command) = skel_args
+        .skeleton
+        .as_deref()
+        .filter(|name| COMMAND_NAMES.contains(name))
+        .filter(|_| skel_args.skeleton_file.is_none())
+    {
+        return Err(Box::from(format!(
+            "{} is a command, give the options after it like `skel {} -a \
+            config.toml`",
+            command, command
+        )));
+    }
+
     if skel_args.skeleton.is_none() && skel_args.skeleton_file.is_none() {
+        // the picker needs a terminal to draw on and to read the keys from
+        if skel_args.name.is_none()
+            && io::stdin().is_terminal()
+            && io::stderr().is_terminal()
+        {
+            skel_args.pick = true;
+
+            return Ok(skel_args);
+        }
+
         return Err(Box::from(String::from(
-            "Error: did not get a skeleton or skeleton-file to make",
+            "did not get a skeleton or skeleton-file to make",
         )));
     }
 
-    if skel_args.name.is_some()
+    if skel_args.target.is_some()
         && skel_args.skeleton_file.is_some()
         && skel_args.skeleton.is_some()
     {
         return Err(Box::from(String::from(
-            "Error: both a skeleton and a skeleton-file given",
+            "both a skeleton and a skeleton-file given",
         )));
     }
 
-    if skel_args.skeleton.is_some()
-        && (skel_args.name.is_none() && skel_args.skeleton_file.is_none())
+    // if `target` is none and `skeleton` and `skeleton_file` is some then the
+    // `skeleton` variable is the `target`
+    if skel_args.target.is_none()
+        && (skel_args.skeleton.is_some() && skel_args.skeleton_file.is_some())
     {
-        return Err(Box::from(String::from("Error: did not get enough args")));
+        skel_args.target = skel_args.skeleton.take();
     }
 
-    if skel_args.skeleton_file.is_some()
-        && (skel_args.skeleton.is_none() && skel_args.name.is_none())
-    {
+    if skel_args.target.is_none() && skel_args.name.is_none() {
         return Err(Box::from(String::from(
-            "Error: did not get a project name to make",
+            "did not get a project name or target to make",
         )));
     }
 
-    // if `name` is none and `skeleton` and `skeleton_file` is some then the
-    // `skeleton` variable is the `name`
-    if skel_args.name.is_none()
-        && (skel_args.skeleton.is_some() && skel_args.skeleton_file.is_some())
-    {
-        skel_args.name = skel_args.skeleton.take();
+    Ok(skel_args)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_command_names() {
+        let mut command = SkelArgs::command();
+
+        command.build();
+
+        let mut names: Vec<&str> =
+            command.get_subcommands().map(|c| c.get_name()).collect();
+        let mut expected = COMMAND_NAMES.to_vec();
+
+        names.sort();
+        expected.sort();
+
+        assert_eq!(names, expected, "COMMAND_NAMES is out of date");
     }
 
-    Ok(skel_args)
+    #[test]
+    fn test_args_beat_commands() {
+        let args = SkelArgs::try_parse_from(["skel", "-s", "s.toml", "list"])
+            .expect("did not parse a target named after a command");
+
+        assert!(args.command.is_none());
+        assert_eq!(args.skeleton.as_deref(), Some("list"));
+
+        let args = SkelArgs::try_parse_from(["skel", "list", "-a", "c.toml"])
+            .expect("did not parse an option after a command");
+
+        assert!(matches!(args.command, Some(SkelCommand::List { .. })));
+        assert_eq!(args.alt_config_path.as_deref(), Some("c.toml"));
+    }
+
+    #[test]
+    fn test_vars() {
+        let args = SkelArgs::try_parse_from([
+            "skel",
+            "rs",
+            "app",
+            "--var",
+            "author=someone",
+            "--var",
+            "url=https://example.com/?a=b",
+        ])
+        .expect("did not parse the vars");
+
+        assert_eq!(
+            args.vars,
+            [
+                ("author".to_string(), "someone".to_string()),
+                ("url".to_string(), "https://example.com/?a=b".to_string()),
+            ]
+        );
+
+        for bad in ["author", "=someone"] {
+            assert!(
+                SkelArgs::try_parse_from(["skel", "rs", "app", "--var", bad])
+                    .is_err(),
+                "some how parsed --var {}",
+                bad
+            );
+        }
+    }
 }
@@ -0,0 +1,584 @@
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+};
+
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    queue,
+    style::{Attribute, Print, SetAttribute},
+    terminal::{self, ClearType},
+};
+
+use crate::{
+    config::{load_main_config, skeleton_vars, SkelVar},
+    error::SkelError,
+    generator::Generator,
+    project_tree::{EntryKind, Plan},
+    report::{Reporter, Verbosity},
+};
+
+// the project name used to preview what a skeleton makes, it is not checked
+// against the skeleton's `name_pattern`
+const PREVIEW_NAME: &str = "my-project";
+
+/// a skeleton that was picked
+#[derive(Debug)]
+pub struct Picked {
+    pub key: String,
+    /// the skeleton file
+    pub path: PathBuf,
+}
+
+// a skeleton that can be picked
+struct Choice {
+    key: String,
+    aliases: Vec<String>,
+    description: Option<String>,
+    tags: Vec<String>,
+}
+
+impl Choice {
+    // the text the query is matched against
+    fn search_text(&self) -> String {
+        let mut text = self.key.clone();
+
+        for part in self.aliases.iter().chain(self.tags.iter()) {
+            text.push(' ');
+            text.push_str(part);
+        }
+
+        if let Some(ref description) = self.description {
+            text.push(' ');
+            text.push_str(description);
+        }
+
+        text
+    }
+
+    fn line(&self) -> String {
+        let mut line = self.key.clone();
+
+        if !self.tags.is_empty() {
+            line.push_str(&format!(" [{}]", self.tags.join(", ")));
+        }
+
+        if let Some(ref description) = self.description {
+            line.push_str(&format!(" - {}", description));
+        }
+
+        line
+    }
+}
+
+// how well the query matches the text, every char of the query has to be in
+// the text in order. chars next to each other and at the start of a word score
+// higher
+fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+
+    let mut score = 0;
+    let mut next = 0;
+    let mut last: Option<usize> = None;
+
+    for query_char in query.to_lowercase().chars() {
+        let found =
+            next + text[next..].iter().position(|c| *c == query_char)?;
+
+        score += 1;
+
+        if last.is_some_and(|last| last + 1 == found) {
+            score += 5;
+        }
+
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+
+        last = Some(found);
+        next = found + 1;
+    }
+
+    // a shorter text is a closer match
+    Some(score * 100 - text.len() as i64)
+}
+
+// the index of each choice that matches the query, best match first
+fn filter_choices(choices: &[Choice], query: &str) -> Vec<usize> {
+    let mut matches: Vec<(i64, usize)> = choices
+        .iter()
+        .enumerate()
+        .filter_map(|(i, choice)| {
+            fuzzy_score(query, &choice.search_text()).map(|score| (score, i))
+        })
+        .collect();
+
+    // keep the name order for the same score
+    matches.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+
+    matches.into_iter().map(|(_, i)| i).collect()
+}
+
+// the planned tree as indented lines, dirs end with a `/`
+fn tree_lines(plan: &Plan) -> Vec<String> {
+    plan.entries
+        .iter()
+        .filter_map(|(path, entry)| {
+            let path = path.strip_prefix(&plan.root).ok()?;
+
+            let depth = path.components().count().saturating_sub(1);
+            let name = path.file_name()?.to_string_lossy();
+
+            let slash = match entry.kind {
+                EntryKind::Dir => "/",
+                _ => "",
+            };
+
+            Some(format!("{}{}{}", "  ".repeat(depth), name, slash))
+        })
+        .collect()
+}
+
+// what a skeleton would make, or why it can't be planned
+fn preview(key: &str, alt_config_path: Option<&Path>) -> Vec<String> {
+    let mut generator = Generator::new()
+        .skeleton(key)
+        .name(PREVIEW_NAME)
+        .no_build(true)
+        .skip_name_check(true)
+        .verbosity(Verbosity::Quiet);
+
+    if let Some(config_path) = alt_config_path {
+        generator = generator.config_path(config_path);
+    }
+
+    match generator.plan() {
+        Ok(plan) => {
+            let mut lines = vec![format!("{}/", PREVIEW_NAME)];
+
+            lines.extend(
+                tree_lines(&plan).into_iter().map(|l| "  ".to_owned() + &l),
+            );
+
+            lines
+        }
+        Err(err) => err.to_string().lines().map(String::from).collect(),
+    }
+}
+
+// cut a line to fit in `width` columns
+fn fit(text: &str, width: usize) -> String {
+    text.chars().take(width).collect()
+}
+
+// raw mode on the alternate screen until dropped
+struct Screen;
+
+impl Screen {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+
+        queue!(io::stderr(), terminal::EnterAlternateScreen, cursor::Hide)?;
+
+        io::stderr().flush()?;
+
+        Ok(Screen)
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ =
+            queue!(io::stderr(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = io::stderr().flush();
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+// the state of the picker between key presses
+struct Picker<'a> {
+    choices: &'a [Choice],
+    alt_config_path: Option<&'a Path>,
+    query: String,
+    matches: Vec<usize>,
+    selected: usize,
+    previews: HashMap<usize, Vec<String>>,
+}
+
+impl<'a> Picker<'a> {
+    fn new(choices: &'a [Choice], alt_config_path: Option<&'a Path>) -> Self {
+        Picker {
+            choices,
+            alt_config_path,
+            query: String::new(),
+            matches: (0..choices.len()).collect(),
+            selected: 0,
+            previews: HashMap::new(),
+        }
+    }
+
+    fn set_query(&mut self, query: String) {
+        self.matches = filter_choices(self.choices, &query);
+        self.query = query;
+        self.selected = 0;
+    }
+
+    fn draw(&mut self, out: &mut impl Write) -> io::Result<()> {
+        let (width, height) = terminal::size()?;
+        let (width, height) = (width as usize, height as usize);
+
+        let list_width = (width * 2 / 5).max(20).min(width);
+        let rows = height.saturating_sub(1);
+
+        let preview = match self.matches.get(self.selected) {
+            Some(&i) => {
+                let (choices, alt) = (self.choices, self.alt_config_path);
+
+                self.previews
+                    .entry(i)
+                    .or_insert_with(|| preview(&choices[i].key, alt))
+                    .clone()
+            }
+            None => vec![],
+        };
+
+        // scroll so the selected skeleton is on the screen
+        let offset = (self.selected + 1).saturating_sub(rows);
+
+        queue!(
+            out,
+            terminal::Clear(ClearType::All),
+            cursor::MoveTo(0, 0),
+            Print(fit(&format!("skeleton> {}", self.query), width))
+        )?;
+
+        for row in 0..rows {
+            queue!(out, cursor::MoveTo(0, row as u16 + 1))?;
+
+            if let Some(&i) = self.matches.get(offset + row) {
+                let line =
+                    fit(&self.choices[i].line(), list_width.saturating_sub(1));
+
+                if offset + row == self.selected {
+                    queue!(
+                        out,
+                        SetAttribute(Attribute::Reverse),
+                        Print(line),
+                        SetAttribute(Attribute::Reset)
+                    )?;
+                } else {
+                    queue!(out, Print(line))?;
+                }
+            }
+
+            if width > list_width + 2 {
+                let line = preview.get(row).map_or("", String::as_str);
+
+                queue!(
+                    out,
+                    cursor::MoveTo(list_width as u16, row as u16 + 1),
+                    Print("│ "),
+                    Print(fit(line, width - list_width - 2))
+                )?;
+            }
+        }
+
+        out.flush()
+    }
+
+    // `Some(None)` when the picker is closed without a pick
+    fn key(&mut self, key: KeyEvent) -> Option<Option<String>> {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+
+        match key.code {
+            KeyCode::Enter => {
+                return self
+                    .matches
+                    .get(self.selected)
+                    .map(|&i| Some(self.choices[i].key.clone()));
+            }
+            KeyCode::Esc => return Some(None),
+            KeyCode::Char('c') if ctrl => return Some(None),
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Char('p' | 'k') if ctrl => {
+                self.selected = self.selected.saturating_sub(1)
+            }
+            KeyCode::Down => self.down(),
+            KeyCode::Char('n' | 'j') if ctrl => self.down(),
+            KeyCode::Backspace => {
+                let mut query = self.query.clone();
+
+                query.pop();
+
+                self.set_query(query);
+            }
+            KeyCode::Char(c) if !ctrl => {
+                self.set_query(format!("{}{}", self.query, c));
+            }
+            _ => {}
+        }
+
+        None
+    }
+
+    fn down(&mut self) {
+        if self.selected + 1 < self.matches.len() {
+            self.selected += 1;
+        }
+    }
+}
+
+/// pick a skeleton from the main configs with a fuzzy search, each skeleton is
+/// shown with its tags and description and a preview of what it makes
+///
+/// `None` when nothing was picked, the terminal has to be interactive
+pub fn pick_skeleton(
+    alt_config_path: Option<&Path>,
+    verbosity: Verbosity,
+) -> Result<Option<Picked>, SkelError> {
+    let reporter = Reporter::new(verbosity, None);
+
+    let (main_config, _) = load_main_config(alt_config_path, &reporter)?;
+
+    let mut choices: Vec<Choice> = main_config
+        .skeletons
+        .iter()
+        .map(|(key, skeleton)| {
+            let meta = skeleton.meta();
+
+            Choice {
+                key: key.clone(),
+                aliases: skeleton.aliases.clone(),
+                description: meta.description,
+                tags: meta.tags.unwrap_or_default(),
+            }
+        })
+        .collect();
+
+    if choices.is_empty() {
+        return Err(SkelError::Other(String::from(
+            "there are no skeletons to pick from",
+        )));
+    }
+
+    choices.sort_by(|a, b| a.key.cmp(&b.key));
+
+    let io_err = |e| SkelError::io(Path::new("terminal"), e);
+
+    let _screen = Screen::enter().map_err(io_err)?;
+
+    let mut picker = Picker::new(&choices, alt_config_path);
+
+    loop {
+        picker.draw(&mut io::stderr()).map_err(io_err)?;
+
+        if let Event::Key(key) = event::read().map_err(io_err)? {
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            if let Some(picked) = picker.key(key) {
+                return Ok(picked.map(|key| Picked {
+                    path: PathBuf::from(&main_config.skeletons[&key].path),
+                    key,
+                }));
+            }
+        }
+    }
+}
+
+/// ask for each variable the picked skeleton declares that was not given with
+/// `--var`, an empty answer keeps the default derived from the project name
+pub fn ask_vars(
+    skel_path: &Path,
+    name: &str,
+    given: &[(String, String)],
+) -> Result<Vec<(String, String)>, SkelError> {
+    let vars: Vec<SkelVar> = skeleton_vars(skel_path, Some(name))?
+        .into_iter()
+        .filter(|var| given.iter().all(|(key, _)| *key != var.key))
+        .collect();
+
+    answer_vars(&vars, &mut io::stdin().lock(), &mut io::stderr())
+        .map_err(|e| SkelError::io(Path::new("stdin"), e))
+}
+
+// ask for each variable on `output` and read the answers from `input`
+fn answer_vars(
+    vars: &[SkelVar],
+    input: &mut dyn BufRead,
+    output: &mut dyn Write,
+) -> io::Result<Vec<(String, String)>> {
+    let mut answers = vec![];
+
+    for var in vars {
+        let default = var.default.clone().unwrap_or_default();
+
+        write!(output, "{} [{}]: ", var.key, default)?;
+        output.flush()?;
+
+        let mut answer = String::new();
+
+        input.read_line(&mut answer)?;
+
+        let answer = match answer.trim_end_matches(['\n', '\r']) {
+            "" => default,
+            answer => answer.to_string(),
+        };
+
+        answers.push((var.key.clone(), answer));
+    }
+
+    Ok(answers)
+}
+
+/// ask for the name of the project to make
+pub fn ask_project_name() -> Result<String, SkelError> {
+    let io_err = |e| SkelError::io(Path::new("stdin"), e);
+
+    eprint!("project name: ");
+    io::stderr().flush().map_err(io_err)?;
+
+    let mut name = String::new();
+
+    io::stdin().read_line(&mut name).map_err(io_err)?;
+
+    match name.trim() {
+        "" => Err(SkelError::Other(String::from("did not get a project name"))),
+        name => Ok(name.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::{collections::BTreeMap, path::PathBuf};
+
+    use crate::{project_tree::PlanEntry, test_utils};
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert!(fuzzy_score("pyt", "python").is_some());
+        assert!(
+            fuzzy_score("nhp", "python").is_none(),
+            "matched out of order"
+        );
+        assert!(fuzzy_score("", "python").is_some());
+
+        assert!(
+            fuzzy_score("rs", "rust-server").unwrap()
+                < fuzzy_score("rs", "rs").unwrap(),
+            "did not score the closer match higher"
+        );
+
+        let choices = [
+            Choice {
+                key: "javascript".into(),
+                aliases: vec!["js".into()],
+                description: None,
+                tags: vec![],
+            },
+            Choice {
+                key: "rust".into(),
+                aliases: vec![],
+                description: Some("a cli".into()),
+                tags: vec!["cli".into()],
+            },
+        ];
+
+        assert_eq!(filter_choices(&choices, "cli"), [1]);
+        assert_eq!(filter_choices(&choices, ""), [0, 1]);
+    }
+
+    #[test]
+    fn test_tree_lines() {
+        let root = PathBuf::from("/tmp/my-project");
+
+        let entry = |kind| PlanEntry {
+            kind,
+            source: String::new(),
+        };
+
+        let entries = BTreeMap::from([
+            (root.join("src"), entry(EntryKind::Dir)),
+            (root.join("src/main.rs"), entry(EntryKind::File)),
+            (
+                root.join("README.md"),
+                entry(EntryKind::Template("".into())),
+            ),
+        ]);
+
+        let plan = Plan {
+            root: root.clone(),
+            skeleton_path: PathBuf::new(),
+            entries,
+            build: None,
+            build_first: false,
+        };
+
+        assert_eq!(tree_lines(&plan), ["README.md", "src/", "  main.rs"]);
+    }
+
+    #[test]
+    fn test_preview_skips_name_check() {
+        let test_data = test_utils::TestData::default();
+        let temp = &test_data.temp_path;
+
+        let config_path = temp.join("config.toml");
+
+        std::fs::write(
+            &config_path,
+            "[skeletons]\nlib.path = \"{{config-dir}}/lib.toml\"\n",
+        )
+        .unwrap();
+
+        // the preview name has a `-` so it does not match
+        std::fs::write(
+            temp.join("lib.toml"),
+            "name_pattern = \"^[a-z]+$\"\ndirs = [\"src\"]\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            preview("lib", Some(&config_path)),
+            ["my-project/", "  src/"],
+            "did not preview a skeleton with a name pattern"
+        );
+    }
+
+    #[test]
+    fn test_answer_vars() {
+        let var = |key: &str, default: &str| SkelVar {
+            key: key.into(),
+            template: String::new(),
+            default: Some(default.into()),
+        };
+
+        let vars = [var("author", "someone"), var("crate_name", "my_project")];
+
+        let mut output = vec![];
+
+        let answers = answer_vars(
+            &vars,
+            &mut io::Cursor::new("\nother_name\n"),
+            &mut output,
+        )
+        .unwrap();
+
+        assert_eq!(
+            answers,
+            [
+                ("author".to_string(), "someone".to_string()),
+                ("crate_name".to_string(), "other_name".to_string()),
+            ],
+            "did not keep the default or take the answer"
+        );
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "author [someone]: crate_name [my_project]: "
+        );
+    }
+}
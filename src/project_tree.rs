@@ -1,300 +1,811 @@
 use std::{
-    collections::HashSet,
+    collections::{BTreeMap, HashMap},
     error::Error,
     fs,
-    io::ErrorKind,
-    path::{Path, PathBuf},
-    process::Command,
+    path::{Component, Path, PathBuf},
 };
 
-use crate::config::{RunConfig, SkelTemplate};
+use crate::{
+    config::{RunConfig, SkelExec, SkelTemplate},
+    edits::{print_edits, run_edits},
+    error::SkelError,
+    exec::{build_failed, check_exec, run_hook},
+    merge::{print_merges, run_merges},
+    output::Output,
+    report::Reporter,
+    steps::{print_steps, run_steps},
+    style::{paint, Stream, Style},
+    templating::Engine,
+    trust::check_trust,
+};
 
-// the derives are to be used in the HashSet
-#[derive(std::cmp::Eq, std::cmp::PartialEq, std::hash::Hash)]
-struct TemplateFile {
-    path: PathBuf,
-    template: String,
+/// what a single path in the project will become
+#[derive(Clone, Debug, PartialEq)]
+pub enum EntryKind {
+    Dir,
+    File,
+    /// a file with the rendered template as its content
+    Template(String),
+}
+
+impl EntryKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EntryKind::Dir => "dir",
+            EntryKind::File => "file",
+            EntryKind::Template(_) => "template",
+        }
+    }
 }
 
-// this is a resolved skeleton
-// this is mostly for `dry_run` so we dont print out duplicates
-// this isnt really necisery but is nice
+/// a path in the project along with the skeleton entry that asked for it
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlanEntry {
+    pub kind: EntryKind,
+    /// where the entry came from in the skeleton, like `dirs[0] "src"`
+    pub source: String,
+}
+
+/// a resolved skeleton, what would be made without anything being made
+///
+/// every path is only in the map once so conflicting entries are found before
+/// anything is made, the map is sorted so parent dirs come before their
+/// children
+#[derive(Clone, Debug)]
+pub struct Plan {
+    /// the project dir
+    pub root: PathBuf,
+    pub skeleton_path: PathBuf,
+    pub entries: BTreeMap<PathBuf, PlanEntry>,
+    /// the build script, `None` if there is not one or builds are skipped
+    pub build: Option<String>,
+    pub build_first: bool,
+}
+
+// the mode for made files, the umask still applies when made in a dir
+const FILE_MODE: u32 = 0o644;
+
+// a plan and what is needed to make it
 struct SkelTree<'build> {
-    dirs: Option<HashSet<PathBuf>>,
-    files: Option<HashSet<PathBuf>>,
-    templates: Option<HashSet<TemplateFile>>,
-    build: Option<&'build str>,
-    build_first: bool,
+    plan: &'build Plan,
+    exec: &'build SkelExec,
+    reporter: &'build Reporter,
+    output: &'build mut dyn Output,
 }
 
-fn run_build_script(build: &str) {
-    let mut build_script = String::from("#!/usr/bin/bash env\n\n");
+// a path that more than one entry wants to be something different
+struct Conflict {
+    path: PathBuf,
+    first: String,
+    second: String,
+    reason: &'static str,
+}
 
-    build_script.push_str(build);
+fn make_conflict_err_msg(conflicts: &[Conflict]) -> String {
+    let mut conflict_str = String::from("conflicting entries found");
 
-    let output = Command::new("bash")
-        .arg("-c")
-        .arg(build_script)
-        .output()
-        .expect("build error");
+    for conflict in conflicts {
+        let new_conflict = format!(
+            "\n{} {}\n    first: {}\n    second: {}",
+            conflict.reason,
+            conflict.path.display(),
+            conflict.first,
+            conflict.second
+        );
 
-    if !output.stdout.is_empty() {
-        println!("{}", String::from_utf8_lossy(&output.stdout));
+        conflict_str.push_str(&new_conflict);
     }
+
+    conflict_str
 }
 
-// collect the template into its own struct
-//
-// this will render the include file
-fn resolved_template(
-    skel_template: &SkelTemplate,
-    run_conf: &RunConfig,
-) -> Result<TemplateFile, Box<dyn Error>> {
-    let template = if let Some(include) = skel_template.include.as_ref() {
-        let template_file_string = match fs::read_to_string(&include) {
-            Err(err) => match err.kind() {
-                ErrorKind::NotFound => {
-                    return Err(Box::from(format!(
-                        "include file not found {}",
-                        include
-                    )));
+// collect every path in to one map and record anything that conflicts
+#[derive(Default)]
+struct TreeBuilder {
+    entries: BTreeMap<PathBuf, PlanEntry>,
+    conflicts: Vec<Conflict>,
+}
+
+impl TreeBuilder {
+    fn add(
+        &mut self,
+        path: PathBuf,
+        kind: EntryKind,
+        source: String,
+        overrides: bool,
+    ) {
+        let existing = match self.entries.get_mut(&path) {
+            None => {
+                self.entries.insert(path, PlanEntry { kind, source });
+
+                return;
+            }
+            Some(existing) => existing,
+        };
+
+        let reason = match (&existing.kind, &kind) {
+            // the same thing asked for twice is fine
+            (old, new) if old == new => return,
+            // a blank file is filled in by a template
+            (EntryKind::Template(_), EntryKind::File) => return,
+            (EntryKind::File, EntryKind::Template(_)) => {
+                *existing = PlanEntry { kind, source };
+
+                return;
+            }
+            (EntryKind::Template(_), EntryKind::Template(_)) => {
+                if overrides {
+                    *existing = PlanEntry { kind, source };
+
+                    return;
                 }
-                _ => return Err(Box::from(err)),
-            },
-            Ok(value) => value,
+
+                "different content for"
+            }
+            _ => "both a dir and a file at",
         };
 
-        run_conf
-            .handle
-            .render_template(&template_file_string, &run_conf.template_data)?
-    } else if let Some(template_str) = &skel_template.template {
-        template_str.clone()
-    } else {
-        return Err(Box::from(String::from(
-            "no template string or include path for template",
-        )));
-    };
+        self.conflicts.push(Conflict {
+            path,
+            first: existing.source.clone(),
+            second: source,
+            reason,
+        });
+    }
 
-    let mut path = run_conf.root_path.clone();
+    // add every parent dir between the root and the path
+    fn add_parents(&mut self, root: &Path, path: &Path, source: &str) {
+        let mut parent = path.parent();
 
-    path.push(&skel_template.path);
+        while let Some(dir) = parent {
+            if dir == root || !dir.starts_with(root) {
+                break;
+            }
+
+            self.add(
+                dir.to_path_buf(),
+                EntryKind::Dir,
+                format!("parent of {}", source),
+                false,
+            );
 
-    let new_template = TemplateFile { path, template };
+            parent = dir.parent();
+        }
+    }
 
-    Ok(new_template)
+    fn finish(self) -> Result<BTreeMap<PathBuf, PlanEntry>, Box<dyn Error>> {
+        if self.conflicts.is_empty() {
+            Ok(self.entries)
+        } else {
+            Err(Box::from(make_conflict_err_msg(&self.conflicts)))
+        }
+    }
 }
 
-// resolve all the templates and add them to a hash set
-fn resolve_templates(
-    run_conf: &RunConfig,
-) -> Result<Option<HashSet<TemplateFile>>, Box<dyn Error>> {
-    if let Some(templates) = run_conf.skel_conf.templates.as_ref() {
-        let mut resolved_templates = HashSet::new();
-        for template in templates {
-            let new_template = resolved_template(template, run_conf)?;
+fn run_build_script(
+    build: &str,
+    exec: &SkelExec,
+    reporter: &Reporter,
+) -> Result<(), Box<dyn Error>> {
+    let output = run_hook("build", build, exec, reporter, true)?;
 
-            resolved_templates.insert(new_template);
-        }
-        Ok(Some(resolved_templates))
+    if output.status.success() {
+        Ok(())
     } else {
-        Ok(None)
+        Err(Box::new(build_failed("build script", &output)))
     }
 }
 
-// add all the dirs to a hash set
-fn resolve_dirs(
-    run_conf: &RunConfig,
-) -> Result<Option<HashSet<PathBuf>>, Box<dyn Error>> {
-    if let Some(dirs) = run_conf.skel_conf.dirs.as_ref() {
-        let mut resolved_dirs = HashSet::new();
-
-        for dir in dirs {
-            let mut dir_path = run_conf.root_path.clone();
+// remove `.` and `..` components from a path without touching the file system
+//
+// a `..` that would go above the start of the path is kept so the caller can
+// tell that the path escapes
+pub(crate) fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                // `/..` is just `/`
+                Some(Component::RootDir) | Some(Component::Prefix(_)) => {}
+                _ => normalized.push(".."),
+            },
+            other => normalized.push(other),
+        }
+    }
 
-            dir_path.push(&dir);
+    normalized
+}
 
-            resolved_dirs.insert(dir_path);
-        }
+// a project can be made in a dir that already exists if it is empty
+pub(crate) fn is_empty_dir(path: &Path) -> bool {
+    fs::read_dir(path).is_ok_and(|mut entries| entries.next().is_none())
+}
 
-        Ok(Some(resolved_dirs))
+// join a skeleton entry on to the project root
+//
+// the entry can be relative to the root or an absolute path that is already
+// inside the root (e.g. "{{root}}/src"), anything that ends up outside of the
+// root is an error
+pub(crate) fn contained_path(
+    root: &Path,
+    entry: &str,
+) -> Result<PathBuf, Box<dyn Error>> {
+    let root = normalize_path(root);
+
+    let path = normalize_path(&root.join(entry));
+
+    if path.starts_with(&root) {
+        Ok(path)
     } else {
-        Ok(None)
+        Err(Box::from(format!(
+            "entry escapes the project root {} -> {}",
+            entry,
+            path.display()
+        )))
     }
 }
 
-type FilesResult =
-    Result<Option<(Vec<PathBuf>, HashSet<PathBuf>)>, Box<dyn Error>>;
+// join a skeleton entry on to the project root like `contained_path`, when
+// `{{root}}` is only the project name like in an archive an entry that starts
+// with it is in the root too
+pub(crate) fn project_path(
+    root: &Path,
+    template_data: &HashMap<String, String>,
+    entry: &str,
+) -> Result<PathBuf, Box<dyn Error>> {
+    let entry = Path::new(entry);
+
+    let entry = match template_data.get("root").map(Path::new) {
+        Some(root_var) if root_var.is_relative() => {
+            entry.strip_prefix(root_var).unwrap_or(entry)
+        }
+        _ => entry,
+    };
+
+    contained_path(root, &entry.to_string_lossy())
+}
 
-// add all the files to a hash set and return the set along with a vec of the
-// parent dirs
+// make sure no existing part of `path` is a symlink that points outside of the
+// project root
 //
-// the parent dirs will be added to the dir hash set
-fn resolve_files(run_conf: &RunConfig) -> FilesResult {
-    if let Some(files) = run_conf.skel_conf.files.as_ref() {
-        let mut resolved_files = HashSet::new();
-        let mut resolved_dirs = vec![];
+// this only matters when making in to a dir that already exists, e.g. after
+// the build script has run or with `--different-root`
+pub(crate) fn check_symlinks(
+    root: &Path,
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let real_root = match fs::canonicalize(root) {
+        Ok(real_root) => real_root,
+        // nothing under the root can exist yet
+        Err(_) => return Ok(()),
+    };
 
-        for file in files {
-            let mut file_path = run_conf.root_path.clone();
+    let relative = path.strip_prefix(root)?;
 
-            file_path.push(&file);
+    let mut current = root.to_path_buf();
 
-            // NOTE: this is probably fine as we have pushed the project root
-            // dir first
-            let parent = file_path.parent().unwrap().to_owned();
+    for component in relative.components() {
+        current.push(component);
 
-            resolved_files.insert(file_path);
-            resolved_dirs.push(parent);
+        let metadata = match fs::symlink_metadata(&current) {
+            Ok(metadata) => metadata,
+            // the rest of the path will be made by us
+            Err(_) => break,
+        };
+
+        if metadata.file_type().is_symlink() {
+            let target = fs::canonicalize(&current).map_err(|_| {
+                format!("dangling symlink in project {}", current.display())
+            })?;
+
+            if !target.starts_with(&real_root) {
+                return Err(Box::from(format!(
+                    "refusing to follow symlink out of the project root {} -> {}",
+                    current.display(),
+                    target.display()
+                )));
+            }
         }
-        Ok(Some((resolved_dirs, resolved_files)))
-    } else {
-        Ok(None)
     }
+
+    Ok(())
 }
 
-fn make_tree(skel_tree: &SkelTree) -> Result<(), Box<dyn Error>> {
-    if skel_tree.build.is_some() && skel_tree.build_first {
-        run_build_script(skel_tree.build.unwrap());
+// render the template or include file in to a string
+pub(crate) fn resolved_template(
+    skel_template: &SkelTemplate,
+    engine: &dyn Engine,
+    template_data: &HashMap<String, String>,
+) -> Result<String, Box<dyn Error>> {
+    if let Some(include) = skel_template.include.as_ref() {
+        let template_file_string = fs::read_to_string(include)
+            .map_err(|e| SkelError::io(Path::new(include), e))?;
+
+        Ok(engine.render(include, &template_file_string, template_data)?)
+    } else if let Some(template_str) = &skel_template.template {
+        Ok(template_str.clone())
+    } else {
+        Err(Box::from(String::from(
+            "no template string or include path for template",
+        )))
     }
+}
 
-    if let Some(ref dirs) = skel_tree.dirs {
-        for dir in dirs {
-            fs::create_dir_all(dir)?;
-        }
-    }
+// resolve all the dirs, files and templates in to a single map
+fn resolve_entries(
+    run_conf: &RunConfig,
+) -> Result<BTreeMap<PathBuf, PlanEntry>, Box<dyn Error>> {
+    let root = normalize_path(&run_conf.root_path);
+    let skel_conf = &run_conf.skel_conf;
 
-    if let Some(ref files) = skel_tree.files {
-        for file in files {
-            fs::File::create(file)?;
-        }
+    let mut builder = TreeBuilder::default();
+
+    for (i, dir) in skel_conf.dirs.iter().flatten().enumerate() {
+        let dir_path = project_path(&root, &run_conf.template_data, dir)?;
+        let source = format!("dirs[{}] \"{}\"", i, dir);
+
+        builder.add_parents(&root, &dir_path, &source);
+        builder.add(dir_path, EntryKind::Dir, source, false);
     }
 
-    if let Some(ref templates) = skel_tree.templates {
-        for template in templates {
-            fs::write(&template.path, &template.template)?;
-        }
+    for (i, file) in skel_conf.files.iter().flatten().enumerate() {
+        let file_path = project_path(&root, &run_conf.template_data, file)?;
+        let source = format!("files[{}] \"{}\"", i, file);
+
+        builder.add_parents(&root, &file_path, &source);
+        builder.add(file_path, EntryKind::File, source, false);
     }
 
-    if skel_tree.build.is_some() && !skel_tree.build_first {
-        run_build_script(skel_tree.build.unwrap());
+    for (i, template) in skel_conf.templates.iter().flatten().enumerate() {
+        let template_path =
+            project_path(&root, &run_conf.template_data, &template.path)?;
+        let source = format!("templates[{}] \"{}\"", i, template.path);
+
+        let content = resolved_template(
+            template,
+            run_conf.engine.as_ref(),
+            &run_conf.template_data,
+        )?;
+
+        builder.add_parents(&root, &template_path, &source);
+        builder.add(
+            template_path,
+            EntryKind::Template(content),
+            source,
+            template.r#override.unwrap_or(false),
+        );
     }
 
-    Ok(())
+    builder.finish()
 }
 
-fn print_tree(root: &Path, skel_tree: &SkelTree) {
-    if root.exists() {
-        println!("\x1b[33mWarning {} already exists\x1b[0m\n", root.display());
+fn make_tree(skel_tree: &mut SkelTree) -> Result<(), Box<dyn Error>> {
+    let plan = skel_tree.plan;
+
+    if plan.build.is_some() && skel_tree.output.dir().is_none() {
+        return Err(Box::from(String::from(
+            "a build script can only be run when making the project in a dir",
+        )));
     }
 
-    println!("would make in to -> {}", root.display());
+    if let (Some(build), true) = (plan.build.as_deref(), plan.build_first) {
+        run_build_script(build, skel_tree.exec, skel_tree.reporter)?;
+    }
 
-    if let Some(ref dirs) = skel_tree.dirs {
-        for dir in dirs {
-            println!("  dir  -> {}", dir.display());
-        }
+    // the root is made after a build first script as it may make the root,
+    // a skeleton with only scripts and steps leaves it to them
+    if !plan.entries.is_empty() {
+        skel_tree
+            .output
+            .create_dir(Path::new(""))
+            .map_err(|e| SkelError::io(&plan.root, e))?;
     }
 
-    if let Some(ref files) = skel_tree.files {
-        for file in files {
-            println!("  file -> {}", file.display());
+    for (path, entry) in plan.entries.iter() {
+        if skel_tree.output.dir().is_some() {
+            check_symlinks(&plan.root, path)?;
         }
+
+        // every entry is already checked to be in the root
+        let relative = path.strip_prefix(&plan.root)?;
+
+        let made =
+            match entry.kind {
+                EntryKind::Dir => skel_tree.output.create_dir(relative),
+                EntryKind::File => {
+                    skel_tree.output.write_file(relative, &[], FILE_MODE)
+                }
+                EntryKind::Template(ref template) => skel_tree
+                    .output
+                    .write_file(relative, template.as_bytes(), FILE_MODE),
+            };
+
+        made.map_err(|e| SkelError::io(path, e))?;
+
+        skel_tree.reporter.created(entry.kind.name(), path);
     }
 
-    if let Some(ref templates) = skel_tree.templates {
-        for template in templates {
-            println!("  ------");
-            println!("  template -> {}", template.path.display());
+    if let (Some(build), false) = (plan.build.as_deref(), plan.build_first) {
+        run_build_script(build, skel_tree.exec, skel_tree.reporter)?;
+    }
 
-            for line in template.template.lines() {
-                println!("    {}", line);
-            }
+    Ok(())
+}
+
+fn print_tree(plan: &Plan, reporter: &Reporter) {
+    let root = &plan.root;
+
+    if root.exists() && !is_empty_dir(root) {
+        reporter.warn(&format!(
+            "{} already exists and is not empty\n",
+            root.display()
+        ));
+    }
+
+    reporter.info(&format!("would make in to -> {}", root.display()));
+
+    for (path, entry) in plan.entries.iter() {
+        match entry.kind {
+            EntryKind::Template(ref template) => {
+                reporter.info("  ------");
+                reporter.info(&format!(
+                    "  {} -> {}",
+                    paint(Style::Kind, Stream::Stdout, "template"),
+                    path.display()
+                ));
+
+                for line in template.lines() {
+                    reporter.info(&format!("    {}", line));
+                }
 
-            println!("  ------");
+                reporter.info("  ------");
+            }
+            ref kind => reporter.info(&format!(
+                "  {} -> {}",
+                paint(
+                    Style::Kind,
+                    Stream::Stdout,
+                    &format!("{:<4}", kind.name())
+                ),
+                path.display()
+            )),
         }
     }
 
-    if let Some(build) = skel_tree.build {
-        println!("  build first = {}", skel_tree.build_first);
+    if let Some(ref build) = plan.build {
+        reporter.info(&format!("  build first = {}", plan.build_first));
 
         for line in build.lines() {
-            println!("    {line}");
+            reporter.info(&format!("    {line}"));
         }
-        println!("  ------");
+
+        reporter.info("  ------");
     }
 }
 
-// collect all the parts of the skeleton in to hash sets
-//
-// the hash sets are to make sure there are no duplicates, this is mostly for
-// printing out the dry run
-pub fn make_project_tree(
+/// resolve the skeleton in to a single map of paths without making anything
+///
+/// conflicting entries are an error
+pub(crate) fn plan_project_tree(
+    run_conf: &RunConfig,
+) -> Result<Plan, SkelError> {
+    let entries = resolve_entries(run_conf)?;
+
+    // `--no-build` skips the build script and run steps completely
+    let build = if run_conf.no_build {
+        None
+    } else {
+        run_conf.skel_conf.build.clone()
+    };
+
+    Ok(Plan {
+        root: run_conf.root_path.clone(),
+        skeleton_path: run_conf.skel_path.clone(),
+        entries,
+        build,
+        build_first: run_conf.skel_conf.build_first.unwrap_or(false),
+    })
+}
+
+/// does making the project need a real dir, scripts, steps, edits, merges and
+/// the build log all work on the file system
+pub(crate) fn needs_dir(run_conf: &RunConfig) -> bool {
+    let skel_conf = &run_conf.skel_conf;
+
+    (skel_conf.build.is_some() && !run_conf.no_build)
+        || skel_conf.steps.is_some()
+        || skel_conf.edits.is_some()
+        || skel_conf.merge.is_some()
+        || run_conf.reporter.has_log()
+}
+
+/// make a planned project in to the output or print what would be made for a
+/// dry run
+pub(crate) fn make_project_tree(
     dry_run: bool,
+    plan: &Plan,
     run_conf: &RunConfig,
-) -> Result<(), Box<dyn Error>> {
-    let mut dirs = resolve_dirs(run_conf)?;
-
-    let files = match resolve_files(run_conf)? {
-        None => None,
-        Some((parent_dirs, files)) => {
-            if let Some(ref mut dirs) = dirs {
-                for dir in parent_dirs.into_iter() {
-                    dirs.insert(dir);
-                }
-            } else {
-                dirs =
-                    Some(parent_dirs.into_iter().collect::<HashSet<PathBuf>>());
-            }
+    output: &mut dyn Output,
+) -> Result<(), SkelError> {
+    if dry_run {
+        print_tree(plan, &run_conf.reporter);
 
-            Some(files)
+        print_steps(run_conf)?;
+
+        print_edits(run_conf)?;
+
+        Ok(print_merges(run_conf)?)
+    } else {
+        if needs_dir(run_conf) && output.dir().is_none() {
+            return Err(SkelError::Other(String::from(
+                "this skeleton can only be made in a dir, it has scripts, \
+                steps, edits, merges or a log file",
+            )));
         }
+
+        check_trust(run_conf)?;
+
+        check_exec(&run_conf.exec)?;
+
+        let mut skel_tree = SkelTree {
+            plan,
+            exec: &run_conf.exec,
+            reporter: &run_conf.reporter,
+            output,
+        };
+
+        let made = make_tree(&mut skel_tree)
+            .and_then(|_| run_steps(run_conf))
+            .and_then(|_| run_edits(run_conf))
+            .and_then(|_| run_merges(run_conf));
+
+        // write the log even if something failed, that is when it is needed
+        let logged = run_conf.reporter.write_log(&run_conf.root_path);
+
+        made.and(logged)?;
+
+        Ok(output.finish().map_err(|e| SkelError::io(&plan.root, e))?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::{
+        config::{SkelConfig, SkelStep},
+        output::DirOutput,
+        test_utils,
     };
 
-    let templates = resolve_templates(run_conf)?;
+    fn test_run_config(skel_conf: SkelConfig) -> RunConfig {
+        RunConfig {
+            skel_conf,
+            root_path: PathBuf::from("/tmp/test_project"),
+            ..Default::default()
+        }
+    }
 
-    // TODO: this is bad and i feel bad
-    if let Some(ref templates) = templates {
-        if let Some(ref mut dirs) = dirs {
-            for template in templates.iter() {
-                // this really shouldn't fail
-                let parent = template
-                    .path
-                    .parent()
-                    .expect("could not get a parent from template path")
-                    .to_owned();
+    fn test_template(path: &str, template: &str) -> SkelTemplate {
+        SkelTemplate {
+            path: path.into(),
+            template: Some(template.into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_contained_path() {
+        let root = Path::new("/tmp/test_project");
+
+        let path = contained_path(root, "src/../src/./main.rs")
+            .expect("did not resolve a path inside the root");
+
+        assert_eq!(path, PathBuf::from("/tmp/test_project/src/main.rs"));
+
+        let path = contained_path(root, "/tmp/test_project/src")
+            .expect("did not allow an absolute path inside the root");
+
+        assert_eq!(path, PathBuf::from("/tmp/test_project/src"));
+    }
+
+    #[test]
+    fn test_contained_path_escapes() {
+        let root = Path::new("/tmp/test_project");
+
+        assert!(
+            contained_path(root, "/etc/profile.d/x.sh").is_err(),
+            "allowed an absolute path outside the root"
+        );
+
+        assert!(
+            contained_path(root, "../../.bashrc").is_err(),
+            "allowed a path to walk out of the root"
+        );
+
+        assert!(
+            contained_path(root, "src/../../test_project_2/file").is_err(),
+            "allowed a sibling dir with the same prefix"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_check_symlinks() {
+        let test_data = test_utils::TestData::default();
+
+        let root = test_data.temp_path.join("test_project");
+        let outside = test_data.temp_path.join("outside");
+
+        fs::create_dir_all(root.join("inside")).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+
+        std::os::unix::fs::symlink(root.join("inside"), root.join("ok_link"))
+            .unwrap();
+        std::os::unix::fs::symlink(&outside, root.join("bad_link")).unwrap();
+
+        assert!(
+            check_symlinks(&root, &root.join("ok_link/file.txt")).is_ok(),
+            "refused a symlink inside the root"
+        );
+
+        assert!(
+            check_symlinks(&root, &root.join("bad_link/file.txt")).is_err(),
+            "followed a symlink out of the root"
+        );
+
+        assert!(
+            check_symlinks(&root, &root.join("new_dir/file.txt")).is_ok(),
+            "refused a path that does not exist yet"
+        );
+    }
+
+    #[test]
+    fn test_resolve_entries() {
+        let run_conf = test_run_config(SkelConfig {
+            dirs: Some(vec!["src".into(), "src/foo".into()]),
+            files: Some(vec!["src/main.rs".into(), "src/foo/bar.rs".into()]),
+            templates: Some(vec![
+                test_template("src/main.rs", "fn main() {}"),
+                test_template("src/main.rs", "fn main() {}"),
+            ]),
+            ..Default::default()
+        });
+
+        let entries =
+            resolve_entries(&run_conf).expect("did not resolve entries");
+
+        let paths: Vec<&Path> = entries.keys().map(|p| p.as_path()).collect();
+
+        assert_eq!(
+            paths,
+            vec![
+                Path::new("/tmp/test_project/src"),
+                Path::new("/tmp/test_project/src/foo"),
+                Path::new("/tmp/test_project/src/foo/bar.rs"),
+                Path::new("/tmp/test_project/src/main.rs"),
+            ]
+        );
+
+        let main_entry = &entries[Path::new("/tmp/test_project/src/main.rs")];
+
+        assert!(
+            main_entry.kind == EntryKind::Template("fn main() {}".into()),
+            "the template did not fill in the blank file"
+        );
+    }
 
-                dirs.insert(parent);
+    #[test]
+    fn test_resolve_entries_conflicting_content() {
+        let run_conf = test_run_config(SkelConfig {
+            templates: Some(vec![
+                test_template("src/main.rs", "fn main() {}"),
+                test_template("src/main.rs", "fn other() {}"),
+            ]),
+            ..Default::default()
+        });
+
+        match resolve_entries(&run_conf) {
+            Ok(_) => panic!("did not find conflicting templates"),
+            Err(err) => {
+                let msg = err.to_string();
+
+                assert!(
+                    msg.contains("templates[0] \"src/main.rs\""),
+                    "{}",
+                    msg
+                );
+                assert!(
+                    msg.contains("templates[1] \"src/main.rs\""),
+                    "{}",
+                    msg
+                );
             }
-        } else {
-            dirs = Some(
-                templates
-                    .iter()
-                    .map(|t| {
-                        t.path
-                            .parent()
-                            .expect("did not get template parent")
-                            .to_owned()
-                    })
-                    .collect::<HashSet<PathBuf>>(),
-            );
         }
     }
 
-    let build_first = run_conf.skel_conf.build_first.unwrap_or(false);
+    #[test]
+    fn test_resolve_entries_override() {
+        let mut overriding = test_template("src/main.rs", "fn other() {}");
 
-    let skel_tree = SkelTree {
-        files,
-        dirs,
-        templates,
-        build_first,
-        build: run_conf.skel_conf.build.as_deref(),
-    };
+        overriding.r#override = Some(true);
 
-    if dry_run {
-        print_tree(&run_conf.root_path, &skel_tree);
+        let run_conf = test_run_config(SkelConfig {
+            templates: Some(vec![
+                test_template("src/main.rs", "fn main() {}"),
+                overriding,
+            ]),
+            ..Default::default()
+        });
 
-        Ok(())
-    } else {
-        make_tree(&skel_tree)
+        let entries = resolve_entries(&run_conf).expect("override did not win");
+
+        let main_entry = &entries[Path::new("/tmp/test_project/src/main.rs")];
+
+        assert!(
+            main_entry.kind == EntryKind::Template("fn other() {}".into()),
+            "the later template did not win"
+        );
+    }
+
+    #[test]
+    fn test_resolve_entries_file_and_dir() {
+        let run_conf = test_run_config(SkelConfig {
+            dirs: Some(vec!["src/foo".into()]),
+            files: Some(vec!["src".into()]),
+            ..Default::default()
+        });
+
+        match resolve_entries(&run_conf) {
+            Ok(_) => panic!("did not find a file and dir at the same path"),
+            Err(err) => {
+                let msg = err.to_string();
+
+                assert!(msg.contains("both a dir and a file"), "{}", msg);
+                assert!(msg.contains("files[0] \"src\""), "{}", msg);
+            }
+        }
+    }
+
+    #[test]
+    fn test_make_steps_only() {
+        let test_data = test_utils::TestData::default();
+
+        let root_path = test_data.temp_path.join("test_project");
+
+        // like `cargo new {{root}}` the step fails when the root is there
+        let mut run_conf = RunConfig {
+            skel_conf: SkelConfig {
+                steps: Some(vec![SkelStep {
+                    run: Some("mkdir {{root}}".into()),
+                    ..Default::default()
+                }]),
+                ..Default::default()
+            },
+            root_path: root_path.clone(),
+            trust: true,
+            ..Default::default()
+        };
+
+        run_conf
+            .template_data
+            .insert("root".into(), root_path.to_string_lossy().into());
+
+        let plan = plan_project_tree(&run_conf).unwrap();
+
+        make_project_tree(
+            false,
+            &plan,
+            &run_conf,
+            &mut DirOutput::new(&root_path),
+        )
+        .expect("made the root before the steps");
+
+        assert!(root_path.is_dir(), "the step did not make the root");
     }
 }
@@ -0,0 +1,291 @@
+use std::{
+    cell::RefCell,
+    error::Error,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    exec::OutputLine,
+    style::{paint, Stream, Style},
+};
+
+/// how much skel should print
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub enum Verbosity {
+    /// only errors
+    Quiet,
+    /// the dry run plan, warnings and build output
+    #[default]
+    Normal,
+    /// everything that is made and when each script starts and finishes
+    Verbose,
+    /// also the files that were read and the stderr of each script
+    Debug,
+}
+
+impl Verbosity {
+    /// turn the `-q` and `-v` flags in to a verbosity
+    pub fn from_flags(quiet: bool, verbose: u8) -> Self {
+        match (quiet, verbose) {
+            (true, _) => Verbosity::Quiet,
+            (false, 0) => Verbosity::Normal,
+            (false, 1) => Verbosity::Verbose,
+            (false, _) => Verbosity::Debug,
+        }
+    }
+}
+
+/// all of the output from making a project goes through here
+///
+/// the build log is kept in memory and written when `write_log` is called so
+/// the log file does not exist before the build script runs, a build script
+/// like `cargo new` would fail if the project dir already existed
+#[derive(Default)]
+pub struct Reporter {
+    verbosity: Verbosity,
+    log_path: Option<PathBuf>,
+    log: RefCell<Vec<String>>,
+    made: RefCell<Vec<PathBuf>>,
+}
+
+// the days since the epoch to a (year, month, day) in the proleptic gregorian
+// calendar
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+// format a time as a utc timestamp like `2022-01-31T13:05:09.042Z`
+fn timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+
+    let secs = since_epoch.as_secs() as i64;
+    let (year, month, day) = civil_from_days(secs.div_euclid(86_400));
+    let day_secs = secs.rem_euclid(86_400);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        day_secs / 3600,
+        day_secs % 3600 / 60,
+        day_secs % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+impl Reporter {
+    pub fn new(verbosity: Verbosity, log_path: Option<PathBuf>) -> Self {
+        Self {
+            verbosity,
+            log_path,
+            log: RefCell::new(vec![]),
+            made: RefCell::new(vec![]),
+        }
+    }
+
+    pub fn verbosity(&self) -> Verbosity {
+        self.verbosity
+    }
+
+    fn log_line(&self, time: SystemTime, line: &str) {
+        if self.log_path.is_some() {
+            self.log.borrow_mut().push(format!(
+                "[{}] {}",
+                timestamp(time),
+                line
+            ));
+        }
+    }
+
+    /// normal output like the dry run plan
+    pub(crate) fn info(&self, msg: &str) {
+        if self.verbosity >= Verbosity::Normal {
+            println!("{}", msg);
+        }
+    }
+
+    /// shown with `-v`
+    pub(crate) fn verbose(&self, msg: &str) {
+        if self.verbosity >= Verbosity::Verbose {
+            println!("{}", msg);
+        }
+    }
+
+    /// shown with `-vv`
+    pub(crate) fn debug(&self, msg: &str) {
+        if self.verbosity >= Verbosity::Debug {
+            eprintln!("{}", msg);
+        }
+    }
+
+    /// a warning goes to stderr unless `-q` is given
+    pub(crate) fn warn(&self, msg: &str) {
+        if self.verbosity >= Verbosity::Normal {
+            eprintln!(
+                "{} {}",
+                paint(Style::Warning, Stream::Stderr, "warning:"),
+                msg
+            );
+        }
+    }
+
+    /// a made dir, file or template, or a changed file
+    pub(crate) fn created(&self, kind: &str, path: &Path) {
+        let kind = paint(Style::Kind, Stream::Stdout, &format!("{:<8}", kind));
+
+        self.verbose(&format!("{} {}", kind, path.display()));
+
+        self.made.borrow_mut().push(path.to_path_buf());
+    }
+
+    /// every path made or changed so far in the order it happened
+    pub(crate) fn take_made(&self) -> Vec<PathBuf> {
+        self.made.borrow_mut().drain(..).collect()
+    }
+
+    /// a build script or run step is starting
+    pub(crate) fn hook_start(&self, name: &str) {
+        self.verbose(&format!("running  {}", name));
+        self.log_line(SystemTime::now(), &format!("{} started", name));
+    }
+
+    /// a build script or run step is done, this prints the stdout and logs
+    /// all of the output
+    pub(crate) fn hook_finish(
+        &self,
+        name: &str,
+        duration: Duration,
+        status: &str,
+        lines: &[OutputLine],
+        print_stdout: bool,
+    ) {
+        for line in lines {
+            let stream = if line.stderr { "stderr" } else { "stdout" };
+
+            self.log_line(
+                line.time,
+                &format!("{} {}: {}", name, stream, line.text),
+            );
+
+            if line.stderr {
+                self.debug(&line.text);
+            } else if print_stdout {
+                self.info(&line.text);
+            }
+        }
+
+        let finished = format!(
+            "{} finished with {} in {:.2}s",
+            name,
+            status,
+            duration.as_secs_f64()
+        );
+
+        self.verbose(&finished);
+        self.log_line(SystemTime::now(), &finished);
+    }
+
+    /// was a log file given
+    pub(crate) fn has_log(&self) -> bool {
+        self.log_path.is_some()
+    }
+
+    /// write the build log if a log file was given
+    ///
+    /// a relative log path is put in the project root
+    pub(crate) fn write_log(&self, root: &Path) -> Result<(), Box<dyn Error>> {
+        let log_path = match self.log_path {
+            Some(ref log_path) => root.join(log_path),
+            None => return Ok(()),
+        };
+
+        if let Some(parent) = log_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut log_file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&log_path)
+            .map_err(|e| {
+                format!("could not open log file {} {}", log_path.display(), e)
+            })?;
+
+        for line in self.log.borrow_mut().drain(..) {
+            writeln!(log_file, "{}", line)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::test_utils;
+
+    #[test]
+    fn test_timestamp() {
+        let time = UNIX_EPOCH + Duration::from_millis(1_643_634_309_042);
+
+        assert_eq!(timestamp(time), "2022-01-31T13:05:09.042Z");
+
+        assert_eq!(timestamp(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+    }
+
+    #[test]
+    fn test_write_log() {
+        let test_data = test_utils::TestData::default();
+
+        let reporter =
+            Reporter::new(Verbosity::Quiet, Some(PathBuf::from("build.log")));
+
+        reporter.hook_start("build");
+        reporter.hook_finish(
+            "build",
+            Duration::from_millis(10),
+            "exit code 0",
+            &[OutputLine {
+                time: SystemTime::now(),
+                stderr: true,
+                text: "warning: something".into(),
+            }],
+            true,
+        );
+
+        reporter.write_log(&test_data.temp_path).unwrap();
+
+        let log =
+            fs::read_to_string(test_data.temp_path.join("build.log")).unwrap();
+
+        let lines: Vec<&str> = log.lines().collect();
+
+        assert_eq!(lines.len(), 3, "{}", log);
+        assert!(lines[0].ends_with("] build started"), "{}", log);
+        assert!(
+            lines[1].ends_with("] build stderr: warning: something"),
+            "{}",
+            log
+        );
+        assert!(
+            lines[2].contains("build finished with exit code 0"),
+            "{}",
+            log
+        );
+    }
+}
@@ -0,0 +1,625 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    env,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::SkelError;
+
+/// a git repo with skeletons in it, `rev` is a branch, tag or commit and is
+/// `HEAD` when it is not given
+#[derive(Deserialize, Clone, Debug)]
+pub struct GitSource {
+    pub git: String,
+    pub rev: Option<String>,
+}
+
+// a source and the commit it is pinned to
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+struct LockedSource {
+    git: String,
+    rev: Option<String>,
+    commit: String,
+}
+
+// the commits every source in a main config is pinned to
+#[derive(Deserialize, Serialize, Default, Debug, PartialEq)]
+struct LockFile {
+    #[serde(default)]
+    sources: BTreeMap<String, LockedSource>,
+}
+
+/// how git sources are used while reading a main config
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum SourceMode {
+    /// only use locked commits that are in the cache, without git and without
+    /// writing the lock file
+    #[default]
+    Cached,
+    /// fetch a source that is not locked or not in the cache yet and lock it
+    Fetch,
+    /// fetch every source again and lock each to the commit its rev points to
+    Update,
+}
+
+/// a source checked out at the commit in the lock file
+#[derive(Clone, Debug)]
+pub struct Checkout {
+    pub name: String,
+    pub rev: String,
+    pub commit: String,
+    pub dir: PathBuf,
+}
+
+/// the dir git sources are kept in, `$XDG_CACHE_HOME/skel`
+pub(crate) fn cache_dir() -> Result<PathBuf, Box<dyn Error>> {
+    let xdg_cache = match env::var_os("XDG_CACHE_HOME") {
+        Some(path) if !path.is_empty() => PathBuf::from(path),
+        _ => match env::var_os("HOME") {
+            Some(home) => PathBuf::from(home).join(".cache"),
+            None => {
+                return Err(Box::from(String::from(
+                    "can not find the cache dir for git sources, set \
+                    XDG_CACHE_HOME or HOME",
+                )))
+            }
+        },
+    };
+
+    Ok(xdg_cache.join("skel"))
+}
+
+/// the lock file for a main config, `config.toml` is locked in `config.lock`
+pub(crate) fn lock_path(config_path: &Path) -> PathBuf {
+    config_path.with_extension("lock")
+}
+
+// the lock file kept in the cache for a config whose own lock can't be written
+fn cache_lock_path(cache_dir: &Path, lock_path: &Path) -> PathBuf {
+    let lock_path =
+        fs::canonicalize(lock_path).unwrap_or_else(|_| lock_path.to_path_buf());
+
+    cache_dir
+        .join("locks")
+        .join(format!("{}.lock", url_hash(&lock_path.to_string_lossy())))
+}
+
+/// a relative path to a repo is relative to the dir of the config it is in,
+/// urls and `host:path` repos are kept as they are
+pub(crate) fn resolve_git_url(git: &str, config_dir: &Path) -> String {
+    let scp_like = match (git.find(':'), git.find('/')) {
+        (Some(colon), Some(slash)) => colon < slash,
+        (Some(_), None) => true,
+        _ => false,
+    };
+
+    if git.contains("://") || scp_like || Path::new(git).is_absolute() {
+        return git.to_string();
+    }
+
+    let path = config_dir.join(git);
+
+    fs::canonicalize(&path)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .to_string()
+}
+
+// run git and return what it printed
+fn git(args: &[&str]) -> Result<String, Box<dyn Error>> {
+    let output = Command::new("git")
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("could not run git {}", e))?;
+
+    if !output.status.success() {
+        return Err(Box::from(format!(
+            "git {} failed\n{}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim_end()
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+// the name of a repo in the cache, the same url always has the same name
+fn url_hash(url: &str) -> String {
+    Sha256::digest(url.as_bytes())
+        .iter()
+        .take(8)
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+// clone the repo as a mirror or fetch it again if it is already cloned
+fn fetch(url: &str, mirror: &Path) -> Result<(), Box<dyn Error>> {
+    let mirror_str = mirror.to_string_lossy();
+
+    if mirror.is_dir() {
+        git(&["--git-dir", &mirror_str, "fetch", "--quiet", "--prune"])?;
+    } else {
+        if let Some(parent) = mirror.parent() {
+            fs::create_dir_all(parent).map_err(|e| SkelError::io(parent, e))?;
+        }
+
+        git(&["clone", "--quiet", "--mirror", "--", url, &mirror_str])?;
+    }
+
+    Ok(())
+}
+
+// the commit a rev points to in the mirror
+fn resolve_rev(mirror: &Path, rev: &str) -> Result<String, Box<dyn Error>> {
+    git(&[
+        "--git-dir",
+        &mirror.to_string_lossy(),
+        "rev-parse",
+        "--verify",
+        "--quiet",
+        &format!("{}^{{commit}}", rev),
+    ])
+    .map_err(|_| Box::from(format!("did not find rev {} in the repo", rev)))
+}
+
+// write the files of a commit in to `dir`, the files are written to a temp dir
+// first so a dir that exists always has every file
+fn extract(
+    mirror: &Path,
+    commit: &str,
+    dir: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let archive = Command::new("git")
+        .args(["--git-dir", &mirror.to_string_lossy(), "archive", commit])
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("could not run git {}", e))?;
+
+    if !archive.status.success() {
+        return Err(Box::from(format!(
+            "git archive {} failed\n{}",
+            commit,
+            String::from_utf8_lossy(&archive.stderr).trim_end()
+        )));
+    }
+
+    let tmp_dir = dir.with_extension("tmp");
+
+    if tmp_dir.exists() {
+        fs::remove_dir_all(&tmp_dir).map_err(|e| SkelError::io(&tmp_dir, e))?;
+    }
+
+    fs::create_dir_all(&tmp_dir).map_err(|e| SkelError::io(&tmp_dir, e))?;
+
+    tar::Archive::new(archive.stdout.as_slice())
+        .unpack(&tmp_dir)
+        .map_err(|e| SkelError::io(&tmp_dir, e))?;
+
+    fs::rename(&tmp_dir, dir).map_err(|e| SkelError::io(dir, e))?;
+
+    Ok(())
+}
+
+// the checkout of one source, git is only used when the locked commit is not
+// in the cache yet or the source is updated. `None` when only the cache is
+// used and the source is not in it
+fn checkout(
+    source: &GitSource,
+    locked: Option<&LockedSource>,
+    cache_dir: &Path,
+    mode: SourceMode,
+) -> Result<Option<LockedSource>, Box<dyn Error>> {
+    let hash = url_hash(&source.git);
+
+    let mirror = cache_dir.join("git").join(format!("{}.git", hash));
+    let checkouts = cache_dir.join("checkouts").join(&hash);
+
+    let rev = source.rev.as_deref().unwrap_or("HEAD");
+
+    // a lock for a different repo or rev is stale
+    let locked = locked
+        .filter(|locked| {
+            mode != SourceMode::Update
+                && locked.git == source.git
+                && locked.rev == source.rev
+        })
+        .map(|locked| locked.commit.clone());
+
+    let commit = match locked {
+        Some(commit) if checkouts.join(&commit).is_dir() => commit,
+        _ if mode == SourceMode::Cached => return Ok(None),
+        Some(commit) => {
+            // only fetch when the mirror does not have the locked commit
+            if resolve_rev(&mirror, &commit).is_err() {
+                fetch(&source.git, &mirror)?;
+            }
+
+            resolve_rev(&mirror, &commit)?
+        }
+        None => {
+            fetch(&source.git, &mirror)?;
+
+            resolve_rev(&mirror, rev)?
+        }
+    };
+
+    let dir = checkouts.join(&commit);
+
+    if !dir.is_dir() {
+        fs::create_dir_all(&checkouts)
+            .map_err(|e| SkelError::io(&checkouts, e))?;
+
+        extract(&mirror, &commit, &dir)?;
+    }
+
+    Ok(Some(LockedSource {
+        git: source.git.clone(),
+        rev: source.rev.clone(),
+        commit,
+    }))
+}
+
+/// check out every source in a main config at the commit in its lock file,
+/// a source that is not locked or is updated is fetched and locked to the
+/// commit its rev points to
+///
+/// only the cache is used when reading, a source that is not in it yet is a
+/// warning and has no checkout. the lock is kept in the cache when the one
+/// next to the config can't be written
+pub(crate) fn checkout_sources(
+    sources: &HashMap<String, GitSource>,
+    lock_path: &Path,
+    cache_dir: &Path,
+    mode: SourceMode,
+    warnings: &mut Vec<String>,
+) -> Result<HashMap<String, Checkout>, Box<dyn Error>> {
+    let cache_lock = cache_lock_path(cache_dir, lock_path);
+
+    // the lock in the cache is newer when it is there
+    let read_path = if cache_lock.is_file() {
+        cache_lock.as_path()
+    } else {
+        lock_path
+    };
+
+    let lock_file: LockFile = match fs::read_to_string(read_path) {
+        Ok(text) => toml::from_str(&text)
+            .map_err(|e| SkelError::parse(read_path, &text, &e))?,
+        Err(_) => LockFile::default(),
+    };
+
+    let mut new_lock = LockFile::default();
+    let mut checkouts = HashMap::new();
+
+    let mut names: Vec<&String> = sources.keys().collect();
+
+    names.sort();
+
+    for name in names {
+        let source = &sources[name];
+
+        let locked = match checkout(
+            source,
+            lock_file.sources.get(name),
+            cache_dir,
+            mode,
+        )
+        .map_err(|e| format!("source {} {} {}", name, source.git, e))?
+        {
+            Some(locked) => locked,
+            None => {
+                warnings.push(format!(
+                    "source {} is not in the cache yet, it is fetched \
+                        when a skeleton is made or with `skel sources update`",
+                    name
+                ));
+
+                continue;
+            }
+        };
+
+        checkouts.insert(
+            name.clone(),
+            Checkout {
+                name: name.clone(),
+                rev: source.rev.clone().unwrap_or_else(|| "HEAD".into()),
+                commit: locked.commit.clone(),
+                dir: cache_dir
+                    .join("checkouts")
+                    .join(url_hash(&source.git))
+                    .join(&locked.commit),
+            },
+        );
+
+        new_lock.sources.insert(name.clone(), locked);
+    }
+
+    let changed = new_lock != lock_file;
+
+    if mode == SourceMode::Cached || (!changed && read_path == lock_path) {
+        return Ok(checkouts);
+    }
+
+    let text = toml::to_string(&new_lock)?;
+
+    // a lock in the cache moves next to the config once it can be written
+    match fs::write(lock_path, &text) {
+        Ok(()) => {
+            if cache_lock.is_file() {
+                fs::remove_file(&cache_lock)
+                    .map_err(|e| SkelError::io(&cache_lock, e))?;
+            }
+        }
+        Err(_) if !changed => {}
+        Err(e) => {
+            warnings.push(format!(
+                "can not write the lock file {}, {}, the sources are locked \
+                in {} instead",
+                lock_path.display(),
+                e,
+                cache_lock.display()
+            ));
+
+            if let Some(parent) = cache_lock.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| SkelError::io(parent, e))?;
+            }
+
+            fs::write(&cache_lock, text)
+                .map_err(|e| SkelError::io(&cache_lock, e))?;
+        }
+    }
+
+    Ok(checkouts)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::test_utils;
+
+    #[test]
+    fn test_checkout_sources() {
+        let test_data = test_utils::TestData::default();
+        let temp = &test_data.temp_path;
+
+        let url = test_utils::make_git_repo(temp);
+
+        let cache = temp.join("cache");
+        let lock = temp.join("config.lock");
+
+        let sources = HashMap::from([(
+            "team".to_string(),
+            GitSource {
+                git: url,
+                rev: Some("v1".into()),
+            },
+        )]);
+
+        let checkouts = checkout_sources(
+            &sources,
+            &lock,
+            &cache,
+            SourceMode::Fetch,
+            &mut vec![],
+        )
+        .expect("did not check out the source");
+
+        let team = &checkouts["team"];
+
+        assert_eq!(
+            fs::read_to_string(team.dir.join("rust/cli.toml")).unwrap(),
+            "dirs = [\"v1\"]\n",
+            "did not check out the rev"
+        );
+
+        let lock_text =
+            fs::read_to_string(&lock).expect("did not write a lock");
+
+        assert!(lock_text.contains(&team.commit), "did not lock the commit");
+
+        // the locked commit is used from the cache without the repo
+        fs::remove_dir_all(temp.join("team.git")).unwrap();
+        fs::remove_dir_all(cache.join("git")).unwrap();
+
+        let offline = checkout_sources(
+            &sources,
+            &lock,
+            &cache,
+            SourceMode::Fetch,
+            &mut vec![],
+        )
+        .expect("did not use the cache");
+
+        assert_eq!(offline["team"].commit, team.commit);
+
+        assert!(
+            checkout_sources(
+                &sources,
+                &lock,
+                &cache,
+                SourceMode::Update,
+                &mut vec![]
+            )
+            .is_err(),
+            "some how updated a source without the repo"
+        );
+    }
+
+    #[test]
+    fn test_update_sources() {
+        let test_data = test_utils::TestData::default();
+        let temp = &test_data.temp_path;
+
+        let url = test_utils::make_git_repo(temp);
+
+        let cache = temp.join("cache");
+        let lock = temp.join("config.lock");
+
+        let mut sources = HashMap::from([(
+            "team".to_string(),
+            GitSource {
+                git: url,
+                rev: Some("v1".into()),
+            },
+        )]);
+
+        let v1 = checkout_sources(
+            &sources,
+            &lock,
+            &cache,
+            SourceMode::Fetch,
+            &mut vec![],
+        )
+        .unwrap();
+
+        // a different rev is not the locked one any more
+        sources.get_mut("team").unwrap().rev = None;
+
+        let main = checkout_sources(
+            &sources,
+            &lock,
+            &cache,
+            SourceMode::Fetch,
+            &mut vec![],
+        )
+        .unwrap();
+
+        assert_ne!(v1["team"].commit, main["team"].commit);
+        assert_eq!(
+            fs::read_to_string(main["team"].dir.join("rust/cli.toml")).unwrap(),
+            "dirs = [\"v2\"]\n"
+        );
+
+        let updated = checkout_sources(
+            &sources,
+            &lock,
+            &cache,
+            SourceMode::Update,
+            &mut vec![],
+        )
+        .unwrap();
+
+        assert_eq!(updated["team"].commit, main["team"].commit);
+    }
+
+    #[test]
+    fn test_cached_sources() {
+        let test_data = test_utils::TestData::default();
+        let temp = &test_data.temp_path;
+
+        let url = test_utils::make_git_repo(temp);
+
+        let cache = temp.join("cache");
+        let lock = temp.join("config.lock");
+
+        let sources = HashMap::from([(
+            "team".to_string(),
+            GitSource {
+                git: url,
+                rev: None,
+            },
+        )]);
+
+        let mut warnings = vec![];
+
+        let cached = checkout_sources(
+            &sources,
+            &lock,
+            &cache,
+            SourceMode::Cached,
+            &mut warnings,
+        )
+        .expect("did not read the cache");
+
+        assert!(
+            cached.is_empty(),
+            "checked out a source that is not fetched"
+        );
+        assert_eq!(warnings.len(), 1, "did not warn about the source");
+        assert!(!cache.exists(), "fetched a source with only the cache");
+        assert!(!lock.exists(), "wrote a lock with only the cache");
+
+        // a lock that can't be written is kept in the cache
+        fs::create_dir(&lock).unwrap();
+
+        let mut warnings = vec![];
+
+        let fetched = checkout_sources(
+            &sources,
+            &lock,
+            &cache,
+            SourceMode::Fetch,
+            &mut warnings,
+        )
+        .expect("did not fetch with a lock that can't be written");
+
+        assert_eq!(warnings.len(), 1, "did not warn about the lock");
+
+        let cache_lock = cache_lock_path(&cache, &lock);
+
+        assert!(
+            fs::read_to_string(&cache_lock)
+                .unwrap()
+                .contains(&fetched["team"].commit),
+            "did not lock the source in the cache"
+        );
+
+        let cached = checkout_sources(
+            &sources,
+            &lock,
+            &cache,
+            SourceMode::Cached,
+            &mut vec![],
+        )
+        .unwrap();
+
+        assert_eq!(cached["team"].commit, fetched["team"].commit);
+
+        // the lock moves next to the config once it can be written
+        fs::remove_dir(&lock).unwrap();
+
+        checkout_sources(
+            &sources,
+            &lock,
+            &cache,
+            SourceMode::Update,
+            &mut vec![],
+        )
+        .unwrap();
+
+        assert!(lock.is_file(), "did not write the lock next to the config");
+        assert!(!cache_lock.exists(), "did not remove the lock in the cache");
+    }
+
+    #[test]
+    fn test_resolve_git_url() {
+        let config_dir = Path::new("/configs");
+
+        assert_eq!(
+            resolve_git_url("skeletons.git", config_dir),
+            "/configs/skeletons.git"
+        );
+        assert_eq!(
+            resolve_git_url("../team/skeletons", config_dir),
+            "/configs/../team/skeletons"
+        );
+
+        for url in [
+            "https://example.com/team/skeletons.git",
+            "file:///srv/skeletons.git",
+            "git@example.com:team/skeletons.git",
+            "/srv/skeletons.git",
+        ] {
+            assert_eq!(resolve_git_url(url, config_dir), url);
+        }
+    }
+}
@@ -0,0 +1,497 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    config::{RunConfig, SkelExec, SkelStep, SkelTemplate},
+    error::SkelError,
+    exec::{build_failed, run_hook},
+    project_tree::{
+        check_symlinks, normalize_path, project_path, resolved_template,
+    },
+    report::Reporter,
+    style::{paint, Stream, Style},
+    templating::Engine,
+};
+
+// a step with all of its strings templated and its paths resolved
+enum Step {
+    Dir(PathBuf),
+    File(PathBuf),
+    Template(PathBuf, String),
+    Copy(PathBuf, PathBuf),
+    Run(String, Option<String>),
+}
+
+fn render(
+    source: &str,
+    template: &str,
+    engine: &dyn Engine,
+    template_data: &HashMap<String, String>,
+) -> Result<String, Box<dyn Error>> {
+    Ok(engine.render(source, template, template_data)?)
+}
+
+// template all the strings in a step and check the paths stay in the root
+fn resolve_step(
+    index: usize,
+    step: &SkelStep,
+    root: &Path,
+    engine: &dyn Engine,
+    template_data: &HashMap<String, String>,
+) -> Result<Step, Box<dyn Error>> {
+    let kinds = [
+        step.dir.is_some(),
+        step.file.is_some(),
+        step.template.is_some(),
+        step.copy.is_some(),
+        step.run.is_some(),
+    ];
+
+    if kinds.iter().filter(|k| **k).count() != 1 {
+        return Err(Box::from(format!(
+            "steps[{}] needs exactly one of dir, file, template, copy or run",
+            index
+        )));
+    }
+
+    if step.capture.is_some() && step.run.is_none() {
+        return Err(Box::from(format!(
+            "steps[{}] can only capture the output of a run step",
+            index
+        )));
+    }
+
+    let source = format!("steps[{}]", index);
+
+    let resolved = if let Some(ref dir) = step.dir {
+        let dir = render(&source, dir, engine, template_data)?;
+
+        Step::Dir(project_path(root, template_data, &dir)?)
+    } else if let Some(ref file) = step.file {
+        let file = render(&source, file, engine, template_data)?;
+
+        Step::File(project_path(root, template_data, &file)?)
+    } else if let Some(ref template) = step.template {
+        let path = render(&source, &template.path, engine, template_data)?;
+
+        let rendered = SkelTemplate {
+            path: path.clone(),
+            template: template
+                .template
+                .as_ref()
+                .map(|t| render(&source, t, engine, template_data))
+                .transpose()?,
+            include: template
+                .include
+                .as_ref()
+                .map(|i| render(&source, i, engine, template_data))
+                .transpose()?,
+            r#override: None,
+        };
+
+        let content = resolved_template(&rendered, engine, template_data)?;
+
+        Step::Template(project_path(root, template_data, &path)?, content)
+    } else if let Some(ref copy) = step.copy {
+        let from = render(&source, &copy.from, engine, template_data)?;
+        let to = render(&source, &copy.to, engine, template_data)?;
+
+        // a relative path is in the skeleton dir, not the current dir
+        let from = match template_data.get("skel-dir") {
+            Some(skel_dir) => Path::new(skel_dir).join(from),
+            None => PathBuf::from(from),
+        };
+
+        Step::Copy(from, project_path(root, template_data, &to)?)
+    } else {
+        let run = step.run.as_deref().unwrap_or_default();
+
+        Step::Run(
+            render(&source, run, engine, template_data)?,
+            step.capture.clone(),
+        )
+    };
+
+    Ok(resolved)
+}
+
+fn make_parent(root: &Path, path: &Path) -> Result<(), Box<dyn Error>> {
+    if let Some(parent) = path.parent() {
+        check_symlinks(root, parent)?;
+
+        fs::create_dir_all(parent)?;
+    }
+
+    Ok(())
+}
+
+// make a link at `to` that points where the link at `from` does
+#[cfg(unix)]
+fn copy_symlink(from: &Path, to: &Path) -> Result<(), Box<dyn Error>> {
+    let target = fs::read_link(from).map_err(|e| SkelError::io(from, e))?;
+
+    std::os::unix::fs::symlink(target, to).map_err(|e| SkelError::io(to, e))?;
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn copy_symlink(from: &Path, _to: &Path) -> Result<(), Box<dyn Error>> {
+    Err(Box::from(format!(
+        "can not copy the symlink {} on this platform",
+        from.display()
+    )))
+}
+
+// copy a file or a whole dir in to the project, symlinks are copied as
+// symlinks so a link back up the tree can't make the copy loop
+fn copy_path(
+    root: &Path,
+    from: &Path,
+    to: &Path,
+) -> Result<(), Box<dyn Error>> {
+    check_symlinks(root, to)?;
+
+    let metadata =
+        fs::symlink_metadata(from).map_err(|e| SkelError::io(from, e))?;
+
+    if metadata.file_type().is_symlink() {
+        make_parent(root, to)?;
+
+        copy_symlink(from, to)?;
+    } else if metadata.is_dir() {
+        fs::create_dir_all(to)?;
+
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+
+            copy_path(root, &entry.path(), &to.join(entry.file_name()))?;
+        }
+    } else {
+        make_parent(root, to)?;
+
+        fs::copy(from, to).map_err(|e| {
+            format!(
+                "could not copy {} -> {} {}",
+                from.display(),
+                to.display(),
+                e
+            )
+        })?;
+    }
+
+    Ok(())
+}
+
+fn run_step(
+    index: usize,
+    root: &Path,
+    step: &Step,
+    exec: &SkelExec,
+    reporter: &Reporter,
+) -> Result<Option<String>, Box<dyn Error>> {
+    match step {
+        Step::Dir(dir) => {
+            check_symlinks(root, dir)?;
+
+            fs::create_dir_all(dir).map_err(|e| SkelError::io(dir, e))?;
+
+            reporter.created("dir", dir);
+        }
+        Step::File(file) => {
+            make_parent(root, file)?;
+            check_symlinks(root, file)?;
+
+            fs::File::create(file).map_err(|e| SkelError::io(file, e))?;
+
+            reporter.created("file", file);
+        }
+        Step::Template(path, content) => {
+            make_parent(root, path)?;
+            check_symlinks(root, path)?;
+
+            fs::write(path, content).map_err(|e| SkelError::io(path, e))?;
+
+            reporter.created("template", path);
+        }
+        Step::Copy(from, to) => {
+            copy_path(root, from, to)?;
+
+            reporter.created("copy", to);
+        }
+        Step::Run(script, capture) => {
+            let name = format!("steps[{}] run", index);
+
+            let output =
+                run_hook(&name, script, exec, reporter, capture.is_none())?;
+
+            if !output.status.success() {
+                return Err(Box::new(build_failed(&name, &output)));
+            }
+
+            if capture.is_some() {
+                let stdout = String::from_utf8_lossy(&output.stdout);
+
+                return Ok(Some(stdout.trim_end().to_string()));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// run the `steps` pipeline in the order they are declared
+///
+/// each step is templated right before it runs so it can use the variables
+/// captured by earlier steps
+pub(crate) fn run_steps(run_conf: &RunConfig) -> Result<(), Box<dyn Error>> {
+    let steps = match run_conf.skel_conf.steps {
+        Some(ref steps) => steps,
+        None => return Ok(()),
+    };
+
+    let root = normalize_path(&run_conf.root_path);
+    let mut template_data = run_conf.template_data.clone();
+
+    for (i, step) in steps.iter().enumerate() {
+        let resolved = resolve_step(
+            i,
+            step,
+            &root,
+            run_conf.engine.as_ref(),
+            &template_data,
+        )?;
+
+        if run_conf.no_build && matches!(resolved, Step::Run(..)) {
+            continue;
+        }
+
+        if let Some(output) =
+            run_step(i, &root, &resolved, &run_conf.exec, &run_conf.reporter)?
+        {
+            if let Step::Run(_, Some(ref var)) = resolved {
+                template_data.insert(var.clone(), output);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// print the `steps` pipeline in the order it would run
+///
+/// captured variables can't be known without running the step so they are
+/// shown as a placeholder
+pub(crate) fn print_steps(run_conf: &RunConfig) -> Result<(), Box<dyn Error>> {
+    let steps = match run_conf.skel_conf.steps {
+        Some(ref steps) => steps,
+        None => return Ok(()),
+    };
+
+    let root = normalize_path(&run_conf.root_path);
+    let mut template_data = run_conf.template_data.clone();
+    let reporter = &run_conf.reporter;
+    let kind = |kind: &str| paint(Style::Kind, Stream::Stdout, kind);
+
+    reporter.info("  steps:");
+
+    for (i, step) in steps.iter().enumerate() {
+        let resolved = resolve_step(
+            i,
+            step,
+            &root,
+            run_conf.engine.as_ref(),
+            &template_data,
+        )?;
+
+        match resolved {
+            Step::Dir(dir) => reporter.info(&format!(
+                "  {:>3}. {} -> {}",
+                i,
+                kind("dir "),
+                dir.display()
+            )),
+            Step::File(file) => reporter.info(&format!(
+                "  {:>3}. {} -> {}",
+                i,
+                kind("file"),
+                file.display()
+            )),
+            Step::Template(path, content) => {
+                reporter.info(&format!(
+                    "  {:>3}. {} -> {}",
+                    i,
+                    kind("template"),
+                    path.display()
+                ));
+
+                for line in content.lines() {
+                    reporter.info(&format!("    {}", line));
+                }
+            }
+            Step::Copy(from, to) => reporter.info(&format!(
+                "  {:>3}. {} {} -> {}",
+                i,
+                kind("copy"),
+                from.display(),
+                to.display()
+            )),
+            Step::Run(..) if run_conf.no_build => reporter.info(&format!(
+                "  {:>3}. {} skipped by --no-build",
+                i,
+                kind("run")
+            )),
+            Step::Run(script, capture) => {
+                match capture {
+                    Some(ref var) => reporter.info(&format!(
+                        "  {:>3}. {} -> {}",
+                        i,
+                        kind("run"),
+                        var
+                    )),
+                    None => {
+                        reporter.info(&format!("  {:>3}. {}", i, kind("run")))
+                    }
+                }
+
+                for line in script.lines() {
+                    reporter.info(&format!("    {}", line));
+                }
+
+                if let Some(var) = capture {
+                    let placeholder = format!("[output of steps[{}]]", i);
+
+                    template_data.insert(var, placeholder);
+                }
+            }
+        }
+    }
+
+    reporter.info("  ------");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::{
+        config::{SkelConfig, SkelCopy},
+        templating::HandlebarsEngine,
+        test_utils,
+    };
+
+    #[test]
+    fn test_run_steps_capture() {
+        let test_data = test_utils::TestData::default();
+
+        let root_path = test_data.temp_path.join("test_project");
+
+        let steps = vec![
+            SkelStep {
+                run: Some("echo 'captured value'".into()),
+                capture: Some("greeting".into()),
+                ..Default::default()
+            },
+            SkelStep {
+                template: Some(SkelTemplate {
+                    path: "{{greeting}}.txt".into(),
+                    template: Some("{{name}} says {{greeting}}".into()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        ];
+
+        let mut run_conf = RunConfig {
+            skel_conf: SkelConfig {
+                steps: Some(steps),
+                ..Default::default()
+            },
+            root_path: root_path.clone(),
+            ..Default::default()
+        };
+
+        run_conf
+            .template_data
+            .insert("name".into(), "test_project".into());
+
+        run_steps(&run_conf).expect("did not run steps");
+
+        let content = fs::read_to_string(root_path.join("captured value.txt"))
+            .expect("did not make the template from the captured value");
+
+        assert_eq!(content, "test_project says captured value");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_copy_step() {
+        let test_data = test_utils::TestData::default();
+        let temp = &test_data.temp_path;
+
+        let skel_dir = temp.join("skel");
+        let root_path = temp.join("test_project");
+
+        fs::create_dir_all(skel_dir.join("files")).unwrap();
+        fs::write(skel_dir.join("files/a.txt"), "a").unwrap();
+
+        // a link back up the tree is copied as a link, not followed
+        std::os::unix::fs::symlink("..", skel_dir.join("files/up")).unwrap();
+
+        let steps = vec![SkelStep {
+            copy: Some(SkelCopy {
+                from: "files".into(),
+                to: "files".into(),
+            }),
+            ..Default::default()
+        }];
+
+        let mut run_conf = RunConfig {
+            skel_conf: SkelConfig {
+                steps: Some(steps),
+                ..Default::default()
+            },
+            root_path: root_path.clone(),
+            ..Default::default()
+        };
+
+        run_conf
+            .template_data
+            .insert("skel-dir".into(), skel_dir.to_string_lossy().into());
+
+        run_steps(&run_conf).expect("did not copy from the skeleton dir");
+
+        assert_eq!(
+            fs::read_to_string(root_path.join("files/a.txt")).unwrap(),
+            "a"
+        );
+        assert_eq!(
+            fs::read_link(root_path.join("files/up")).unwrap(),
+            Path::new(".."),
+            "did not copy the symlink as a symlink"
+        );
+    }
+
+    #[test]
+    fn test_resolve_step_needs_one_kind() {
+        let step = SkelStep {
+            dir: Some("src".into()),
+            file: Some("src/main.rs".into()),
+            ..Default::default()
+        };
+
+        let engine = HandlebarsEngine::new();
+
+        assert!(
+            resolve_step(0, &step, Path::new("/tmp"), &engine, &HashMap::new())
+                .is_err(),
+            "allowed a step with two kinds"
+        );
+    }
+}
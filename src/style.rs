@@ -0,0 +1,174 @@
+use std::{
+    env,
+    ffi::OsString,
+    io::{self, IsTerminal},
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+use clap::ValueEnum;
+
+/// when to use color, given with `--color`
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum ColorChoice {
+    /// color a stream if it is a terminal
+    #[default]
+    Auto,
+    /// always color, even when piped
+    Always,
+    /// never color
+    Never,
+}
+
+/// the stream some text will be printed to
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// the kinds of text that get colored
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Style {
+    Error,
+    Warning,
+    /// the kind of a plan entry like `dir` or `template`
+    Kind,
+    /// a highlighted value in a message like a duplicate key
+    Value,
+    Added,
+    Removed,
+    /// a hunk header in a diff
+    Hunk,
+}
+
+// the color choice for the whole process, set once from the args
+static COLOR_CHOICE: AtomicU8 = AtomicU8::new(ColorChoice::Auto as u8);
+
+/// set the color choice for everything printed after this
+pub fn set_color_choice(choice: ColorChoice) {
+    COLOR_CHOICE.store(choice as u8, Ordering::Relaxed);
+}
+
+fn color_choice() -> ColorChoice {
+    match COLOR_CHOICE.load(Ordering::Relaxed) {
+        n if n == ColorChoice::Always as u8 => ColorChoice::Always,
+        n if n == ColorChoice::Never as u8 => ColorChoice::Never,
+        _ => ColorChoice::Auto,
+    }
+}
+
+// `--color` wins, then `NO_COLOR` if it is set and not empty, then
+// `CLICOLOR_FORCE` if it is set and not `0`, then if the stream is a terminal
+fn should_color(
+    choice: ColorChoice,
+    is_terminal: bool,
+    no_color: Option<OsString>,
+    clicolor_force: Option<OsString>,
+) -> bool {
+    match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            if no_color.is_some_and(|v| !v.is_empty()) {
+                false
+            } else if clicolor_force.is_some_and(|v| v != "0" && !v.is_empty())
+            {
+                true
+            } else {
+                is_terminal
+            }
+        }
+    }
+}
+
+fn use_color(stream: Stream) -> bool {
+    let is_terminal = match stream {
+        Stream::Stdout => io::stdout().is_terminal(),
+        Stream::Stderr => io::stderr().is_terminal(),
+    };
+
+    should_color(
+        color_choice(),
+        is_terminal,
+        env::var_os("NO_COLOR"),
+        env::var_os("CLICOLOR_FORCE"),
+    )
+}
+
+fn style_code(style: Style) -> &'static str {
+    match style {
+        Style::Error => "1;31",
+        Style::Warning => "1;33",
+        Style::Kind => "36",
+        Style::Value => "31",
+        Style::Added => "32",
+        Style::Removed => "31",
+        Style::Hunk => "35",
+    }
+}
+
+/// style some text that will be printed to `stream`, the text is left alone
+/// if that stream should not be colored
+pub fn paint(style: Style, stream: Stream, text: &str) -> String {
+    if use_color(stream) {
+        format!("\x1b[{}m{}\x1b[0m", style_code(style), text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// style a line of a unified diff by its first char
+pub(crate) fn paint_diff_line(stream: Stream, line: &str) -> String {
+    if line.starts_with("@@") {
+        paint(Style::Hunk, stream, line)
+    } else if line.starts_with('+') {
+        paint(Style::Added, stream, line)
+    } else if line.starts_with('-') {
+        paint(Style::Removed, stream, line)
+    } else {
+        line.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_should_color() {
+        assert!(should_color(ColorChoice::Auto, true, None, None));
+        assert!(!should_color(ColorChoice::Auto, false, None, None));
+
+        assert!(
+            !should_color(ColorChoice::Auto, true, Some("1".into()), None),
+            "did not honor NO_COLOR"
+        );
+
+        assert!(
+            should_color(ColorChoice::Auto, true, Some("".into()), None),
+            "an empty NO_COLOR turned off color"
+        );
+
+        assert!(
+            should_color(ColorChoice::Auto, false, None, Some("1".into())),
+            "did not honor CLICOLOR_FORCE"
+        );
+
+        assert!(!should_color(
+            ColorChoice::Auto,
+            false,
+            None,
+            Some("0".into())
+        ));
+
+        assert!(
+            should_color(ColorChoice::Always, false, Some("1".into()), None),
+            "--color=always did not win over NO_COLOR"
+        );
+
+        assert!(
+            !should_color(ColorChoice::Never, true, None, Some("1".into())),
+            "--color=never did not win over CLICOLOR_FORCE"
+        );
+    }
+}
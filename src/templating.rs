@@ -1,9 +1,65 @@
-use std::env;
+use std::{collections::HashMap, env, error::Error};
 
 use handlebars::{
-    Context, Handlebars, Helper, JsonRender, Output, RenderContext, RenderError,
+    handlebars_helper, Context, Handlebars, Helper, JsonRender, Output,
+    RenderContext, RenderError,
 };
 
+use serde::Deserialize;
+
+use crate::error::SkelError;
+
+/// the template engine a skeleton is rendered with, given with `engine` in
+/// the skeleton
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum EngineKind {
+    #[default]
+    Handlebars,
+    Minijinja,
+    Tera,
+}
+
+impl EngineKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "handlebars" => Some(EngineKind::Handlebars),
+            "minijinja" => Some(EngineKind::Minijinja),
+            "tera" => Some(EngineKind::Tera),
+            _ => None,
+        }
+    }
+
+    pub fn engine(self) -> Box<dyn Engine> {
+        match self {
+            EngineKind::Handlebars => Box::new(HandlebarsEngine::new()),
+            EngineKind::Minijinja => Box::new(MinijinjaEngine),
+            EngineKind::Tera => Box::new(TeraEngine),
+        }
+    }
+}
+
+/// something that can render a template string with the template variables
+///
+/// every engine has the `env` helper, the case helpers and the built in
+/// variables, the jinja style engines also get `config_dir` and `skel_dir` as
+/// names with a `-` can not be used there
+pub trait Engine {
+    /// render `template`, `source` is where the template came from for errors
+    fn render(
+        &self,
+        source: &str,
+        template: &str,
+        data: &HashMap<String, String>,
+    ) -> Result<String, SkelError>;
+}
+
+impl Default for Box<dyn Engine> {
+    fn default() -> Self {
+        EngineKind::default().engine()
+    }
+}
+
 fn env_helper(
     h: &Helper,
     _: &Handlebars,
@@ -33,10 +89,434 @@ fn env_helper(
     }
 }
 
+// split a name in to lowercase words at anything that is not a letter or a
+// number and at the start of a capital like `fooBar` or `HTTPServer`
+fn words(name: &str) -> Vec<String> {
+    let chars: Vec<char> = name.chars().collect();
+    let mut words = vec![];
+    let mut word = String::new();
+
+    for (i, c) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            if !word.is_empty() {
+                words.push(word.to_lowercase());
+                word.clear();
+            }
+
+            continue;
+        }
+
+        let prev = i.checked_sub(1).map(|i| chars[i]);
+        let next = chars.get(i + 1);
+
+        let boundary = c.is_uppercase()
+            && prev.is_some_and(|p| {
+                p.is_lowercase()
+                    || p.is_numeric()
+                    || (p.is_uppercase()
+                        && next.is_some_and(|n| n.is_lowercase()))
+            });
+
+        if boundary && !word.is_empty() {
+            words.push(word.to_lowercase());
+            word.clear();
+        }
+
+        word.push(*c);
+    }
+
+    if !word.is_empty() {
+        words.push(word.to_lowercase());
+    }
+
+    words
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// a helper that changes the case of a name
+pub type CaseHelper = fn(&str) -> String;
+
+/// the case helpers in every engine, called like `{{snake_case name}}` in
+/// handlebars and `{{ name | snake_case }}` in the jinja style engines
+pub const CASE_HELPERS: [(&str, CaseHelper); 5] = [
+    ("snake_case", snake_case),
+    ("kebab_case", kebab_case),
+    ("camel_case", camel_case),
+    ("pascal_case", pascal_case),
+    ("upper_snake_case", upper_snake_case),
+];
+
+pub fn snake_case(name: &str) -> String {
+    words(name).join("_")
+}
+
+pub fn kebab_case(name: &str) -> String {
+    words(name).join("-")
+}
+
+pub fn camel_case(name: &str) -> String {
+    let words = words(name);
+
+    let mut camel = words.first().cloned().unwrap_or_default();
+
+    for word in words.iter().skip(1) {
+        camel.push_str(&capitalize(word));
+    }
+
+    camel
+}
+
+pub fn pascal_case(name: &str) -> String {
+    words(name).iter().map(|word| capitalize(word)).collect()
+}
+
+pub fn upper_snake_case(name: &str) -> String {
+    snake_case(name).to_uppercase()
+}
+
+handlebars_helper!(snake_case_helper: |name: str| snake_case(name));
+handlebars_helper!(kebab_case_helper: |name: str| kebab_case(name));
+handlebars_helper!(camel_case_helper: |name: str| camel_case(name));
+handlebars_helper!(pascal_case_helper: |name: str| pascal_case(name));
+handlebars_helper!(upper_snake_case_helper: |name: str| upper_snake_case(name));
+
 pub fn instantiate_handlebars<'reg>() -> Handlebars<'reg> {
     let mut handle = Handlebars::new();
 
     handle.register_helper("env", Box::from(env_helper));
 
+    handle.register_helper("snake_case", Box::new(snake_case_helper));
+    handle.register_helper("kebab_case", Box::new(kebab_case_helper));
+    handle.register_helper("camel_case", Box::new(camel_case_helper));
+    handle.register_helper("pascal_case", Box::new(pascal_case_helper));
     handle
+        .register_helper("upper_snake_case", Box::new(upper_snake_case_helper));
+
+    handle
+}
+
+pub struct HandlebarsEngine {
+    handle: Handlebars<'static>,
+}
+
+impl HandlebarsEngine {
+    pub fn new() -> Self {
+        HandlebarsEngine {
+            handle: instantiate_handlebars(),
+        }
+    }
+}
+
+impl Default for HandlebarsEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Engine for HandlebarsEngine {
+    fn render(
+        &self,
+        source: &str,
+        template: &str,
+        data: &HashMap<String, String>,
+    ) -> Result<String, SkelError> {
+        self.handle
+            .render_template(template, data)
+            .map_err(|e| SkelError::render(source, &e))
+    }
+}
+
+// the template data with a `_` version of every name with a `-` in it
+fn jinja_data(data: &HashMap<String, String>) -> HashMap<String, String> {
+    let mut jinja_data = data.clone();
+
+    for (key, value) in data.iter().filter(|(key, _)| key.contains('-')) {
+        jinja_data
+            .entry(key.replace('-', "_"))
+            .or_insert_with(|| value.clone());
+    }
+
+    jinja_data
+}
+
+fn env_var(var: &str) -> Result<String, String> {
+    env::var(var).map_err(|_| format!("did not find env var called {}", var))
+}
+
+// the message of an error and all of its sources
+fn error_chain(err: &dyn Error) -> String {
+    let mut message = err.to_string();
+    let mut source = err.source();
+
+    while let Some(err) = source {
+        message.push_str(": ");
+        message.push_str(&err.to_string());
+
+        source = err.source();
+    }
+
+    message
+}
+
+/// jinja2 style templates with filters, inline expressions and macros, the
+/// `env` helper is a function like `{{ env("HOME") }}`
+pub struct MinijinjaEngine;
+
+impl Engine for MinijinjaEngine {
+    fn render(
+        &self,
+        source: &str,
+        template: &str,
+        data: &HashMap<String, String>,
+    ) -> Result<String, SkelError> {
+        let mut jinja_env = minijinja::Environment::new();
+
+        jinja_env.set_keep_trailing_newline(true);
+
+        jinja_env.add_function("env", |var: String| {
+            env_var(&var).map_err(|e| {
+                minijinja::Error::new(minijinja::ErrorKind::InvalidOperation, e)
+            })
+        });
+
+        for (name, helper) in CASE_HELPERS {
+            jinja_env.add_filter(name, move |value: String| helper(&value));
+        }
+
+        jinja_env
+            .render_str(template, jinja_data(data))
+            .map_err(|e| SkelError::Render {
+                template: source.to_string(),
+                variable: None,
+                message: error_chain(&e),
+            })
+    }
+}
+
+// pull the variable name out of a tera error if there is one
+fn tera_variable(message: &str) -> Option<String> {
+    let (_, rest) = message.split_once("Variable `")?;
+
+    rest.split('`').next().map(String::from)
+}
+
+/// jinja2 style templates using tera, the `env` helper is a function with a
+/// named argument like `{{ env(name="HOME") }}`
+pub struct TeraEngine;
+
+impl Engine for TeraEngine {
+    fn render(
+        &self,
+        source: &str,
+        template: &str,
+        data: &HashMap<String, String>,
+    ) -> Result<String, SkelError> {
+        let mut tera = tera::Tera::default();
+
+        tera.autoescape_on(vec![]);
+
+        tera.register_function("env", |args: &HashMap<String, tera::Value>| {
+            match args.get("name") {
+                Some(tera::Value::String(var)) => {
+                    env_var(var).map(tera::Value::String).map_err(Into::into)
+                }
+                _ => {
+                    Err("env needs a name argument like env(name=\"HOME\")"
+                        .into())
+                }
+            }
+        });
+
+        for (name, helper) in CASE_HELPERS {
+            tera.register_filter(
+                name,
+                move |value: &tera::Value, _: &HashMap<String, tera::Value>| {
+                    match value {
+                        tera::Value::String(value) => {
+                            Ok(tera::Value::String(helper(value)))
+                        }
+                        _ => Err(format!("{} needs a string", name).into()),
+                    }
+                },
+            );
+        }
+
+        let render_err = |message: String| SkelError::Render {
+            template: source.to_string(),
+            variable: tera_variable(&message),
+            message,
+        };
+
+        let context = tera::Context::from_serialize(jinja_data(data))
+            .map_err(|e| render_err(error_chain(&e)))?;
+
+        tera.render_str(template, &context)
+            .map_err(|e| render_err(error_chain(&e)))
+    }
+}
+
+/// find `engine = "..."` in a skeleton before it is templated
+///
+/// only the top level keys before the first table are looked at, handlebars
+/// is used if there is no engine
+pub(crate) fn engine_from_source(text: &str) -> Result<EngineKind, String> {
+    for line in text.lines().map(str::trim) {
+        if line.starts_with('[') {
+            break;
+        }
+
+        let value = match line
+            .strip_prefix("engine")
+            .and_then(|rest| rest.trim_start().strip_prefix('='))
+        {
+            Some(value) => value.trim(),
+            None => continue,
+        };
+
+        let name = value
+            .split('#')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .trim_matches(|c| c == '"' || c == '\'');
+
+        return EngineKind::from_name(name).ok_or_else(|| {
+            format!(
+                "unknown template engine {}, expected handlebars, minijinja \
+                or tera",
+                name
+            )
+        });
+    }
+
+    Ok(EngineKind::default())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn test_data() -> HashMap<String, String> {
+        let mut data = HashMap::new();
+
+        data.insert("name".to_string(), "test_project".to_string());
+        data.insert("skel-dir".to_string(), "/tmp/skel".to_string());
+
+        data
+    }
+
+    #[test]
+    fn test_engines_render() {
+        let data = test_data();
+
+        let cases = [
+            (EngineKind::Handlebars, "{{name}} {{skel-dir}}"),
+            (EngineKind::Minijinja, "{{ name }} {{ skel_dir }}"),
+            (EngineKind::Tera, "{{ name }} {{ skel_dir }}"),
+        ];
+
+        for (kind, template) in cases {
+            let rendered = kind
+                .engine()
+                .render("test", template, &data)
+                .unwrap_or_else(|e| panic!("{:?} did not render {}", kind, e));
+
+            assert_eq!(rendered, "test_project /tmp/skel", "{:?}", kind);
+        }
+
+        let rendered = EngineKind::Minijinja
+            .engine()
+            .render("test", "{{ name | upper }}", &data)
+            .expect("did not render a minijinja filter");
+
+        assert_eq!(rendered, "TEST_PROJECT");
+    }
+
+    #[test]
+    fn test_engines_env() {
+        let data = test_data();
+
+        let path = env::var("PATH").expect("did not get PATH");
+
+        let cases = [
+            (EngineKind::Handlebars, "{{env \"PATH\"}}"),
+            (EngineKind::Minijinja, "{{ env(\"PATH\") }}"),
+            (EngineKind::Tera, "{{ env(name=\"PATH\") }}"),
+        ];
+
+        for (kind, template) in cases {
+            let rendered = kind
+                .engine()
+                .render("test", template, &data)
+                .unwrap_or_else(|e| panic!("{:?} did not render {}", kind, e));
+
+            assert_eq!(rendered, path, "{:?} did not use the env helper", kind);
+        }
+    }
+
+    #[test]
+    fn test_case_helpers() {
+        assert_eq!(snake_case("my-New Project"), "my_new_project");
+        assert_eq!(snake_case("HTTPServer2go"), "http_server2go");
+        assert_eq!(kebab_case("my_new_project"), "my-new-project");
+        assert_eq!(camel_case("my-new-project"), "myNewProject");
+        assert_eq!(pascal_case("my-new-project"), "MyNewProject");
+        assert_eq!(upper_snake_case("myNewProject"), "MY_NEW_PROJECT");
+
+        let data = test_data();
+
+        let cases = [
+            (EngineKind::Handlebars, "{{pascal_case name}}"),
+            (EngineKind::Minijinja, "{{ name | pascal_case }}"),
+            (EngineKind::Tera, "{{ name | pascal_case }}"),
+        ];
+
+        for (kind, template) in cases {
+            let rendered = kind
+                .engine()
+                .render("test", template, &data)
+                .unwrap_or_else(|e| panic!("{:?} did not render {}", kind, e));
+
+            assert_eq!(rendered, "TestProject", "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn test_tera_render_error() {
+        let err = EngineKind::Tera
+            .engine()
+            .render("test", "{{ missing }}", &test_data())
+            .unwrap_err();
+
+        match err {
+            SkelError::Render { variable, .. } => {
+                assert_eq!(variable, Some("missing".into()))
+            }
+            _ => panic!("did not make a render error {}", err),
+        }
+    }
+
+    #[test]
+    fn test_engine_from_source() {
+        assert_eq!(
+            engine_from_source("dirs = [\"src\"]\nengine = \"tera\"\n"),
+            Ok(EngineKind::Tera)
+        );
+
+        assert_eq!(
+            engine_from_source("dirs = []\n[[templates]]\nengine = \"tera\"\n"),
+            Ok(EngineKind::Handlebars),
+            "used an engine key from a table"
+        );
+
+        assert!(engine_from_source("engine = \"jinja\"").is_err());
+    }
 }
@@ -1,8 +1,12 @@
-use std::{env, fs, path::PathBuf};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
 
 use tempfile::{tempdir, TempDir};
 
-use crate::parse_args::SkelArgs;
+use crate::generator::Generator;
 
 pub const TEST_PROJECT_KEY: &str = "test_project";
 pub const TEST_PROJECT_PATH: &str = "{{config-dir}}/projects/test_project.toml";
@@ -41,6 +45,8 @@ pub const TEST_INCLUDE_NAME: &str = "projects/test_include_file.txt";
 pub const TEST_INCLUDE_STR: &str = "this is the include test file for {{name}}";
 
 pub struct TestData {
+    // held so the temp dir is not removed until the test data is dropped
+    #[allow(dead_code)]
     pub temp_dir: TempDir,
     pub temp_path: PathBuf,
     pub temp_path_string: String,
@@ -112,11 +118,54 @@ impl TestData {
     }
 }
 
-pub fn test_args() -> SkelArgs {
-    SkelArgs {
-        skeleton: Some(String::from("t")),
-        name: Some(String::from("test_project")),
-        dry_run: true,
-        ..Default::default()
-    }
+pub fn test_generator() -> Generator {
+    Generator::new()
+        .skeleton("t")
+        .name("test_project")
+        .dry_run(true)
+}
+
+// run git in `dir` as a test user
+fn git_in(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .status()
+        .expect("could not run git");
+
+    assert!(status.success(), "git {} failed", args.join(" "));
+}
+
+/// a bare repo in `temp` with `rust/cli.toml` making a `v1` dir at the tag
+/// `v1` and a `v2` dir on `main`, returns the `file://` url
+pub fn make_git_repo(temp: &Path) -> String {
+    let work = temp.join("work");
+    let bare = temp.join("team.git");
+
+    fs::create_dir_all(work.join("rust")).unwrap();
+
+    git_in(&work, &["init", "--quiet", "--initial-branch", "main"]);
+
+    fs::write(work.join("rust/cli.toml"), "dirs = [\"v1\"]\n").unwrap();
+    git_in(&work, &["add", "."]);
+    git_in(&work, &["commit", "--quiet", "-m", "v1"]);
+    git_in(&work, &["tag", "v1"]);
+
+    fs::write(work.join("rust/cli.toml"), "dirs = [\"v2\"]\n").unwrap();
+    git_in(&work, &["commit", "--quiet", "-am", "v2"]);
+
+    git_in(
+        temp,
+        &[
+            "clone",
+            "--quiet",
+            "--bare",
+            &work.to_string_lossy(),
+            &bare.to_string_lossy(),
+        ],
+    );
+
+    format!("file://{}", bare.display())
 }
@@ -0,0 +1,329 @@
+use std::{
+    env,
+    error::Error,
+    fs,
+    io::{self, IsTerminal, Write},
+    path::{Path, PathBuf},
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::config::{RunConfig, SkelConfig};
+
+// all the scripts a skeleton can run, before templating where possible so a
+// new project name does not count as a changed script
+fn skeleton_scripts(skel_conf: &SkelConfig) -> Vec<&str> {
+    let mut scripts = vec![];
+
+    if let Some(build) =
+        skel_conf.raw_build.as_ref().or(skel_conf.build.as_ref())
+    {
+        scripts.push(build.as_str());
+    }
+
+    for step in skel_conf.steps.iter().flatten() {
+        if let Some(ref run) = step.run {
+            scripts.push(run.as_str());
+        }
+    }
+
+    scripts
+}
+
+// everything else that decides what the scripts do, the derived variables
+// they can use and how they are run
+fn script_settings(skel_conf: &SkelConfig) -> Vec<String> {
+    let mut settings = vec![];
+
+    let derived = skel_conf
+        .raw_derived
+        .as_ref()
+        .or(skel_conf.derived.as_ref());
+
+    for (key, value) in derived.iter().flat_map(|derived| derived.iter()) {
+        settings.push(format!("derived.{} = {}", key, value));
+    }
+
+    if let Some(ref exec) = skel_conf.exec {
+        settings.push(format!("exec.clear_env = {:?}", exec.clear_env));
+        settings.push(format!("exec.env_allow = {:?}", exec.env_allow));
+        settings.push(format!("exec.timeout = {:?}", exec.timeout));
+        settings.push(format!("exec.umask = {:?}", exec.umask));
+        settings.push(format!("exec.limits = {:?}", exec.limits));
+    }
+
+    settings
+}
+
+// a hash of all the scripts in a skeleton and what they depend on or `None` if
+// there are no scripts
+fn scripts_hash(skel_conf: &SkelConfig) -> Option<String> {
+    let scripts = skeleton_scripts(skel_conf);
+
+    if scripts.is_empty() {
+        return None;
+    }
+
+    let settings = script_settings(skel_conf);
+
+    let mut hasher = Sha256::new();
+
+    for part in scripts
+        .into_iter()
+        .chain(settings.iter().map(String::as_str))
+    {
+        hasher.update(part.as_bytes());
+        // keep `a` + `bc` different from `ab` + `c`
+        hasher.update([0]);
+    }
+
+    let hash = hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+
+    Some(hash)
+}
+
+// get the path to the trusted file in `$XDG_DATA_HOME/skel/trusted`
+fn trusted_path() -> Result<PathBuf, Box<dyn Error>> {
+    // an empty `XDG_DATA_HOME` is the same as not setting it
+    let mut xdg_data = match env::var("XDG_DATA_HOME") {
+        Ok(path) if !path.is_empty() => PathBuf::from(path),
+        _ => {
+            let home = env::var("HOME")?;
+
+            let mut home_path = PathBuf::from(home);
+
+            home_path.push(".local");
+            home_path.push("share");
+
+            home_path
+        }
+    };
+
+    xdg_data.push("skel");
+    xdg_data.push("trusted");
+
+    Ok(xdg_data)
+}
+
+// each line in the trusted file is `<hash> <skeleton path>`
+fn is_trusted(trusted: &Path, skel_path: &Path, hash: &str) -> bool {
+    let trusted_string = match fs::read_to_string(trusted) {
+        Ok(trusted_string) => trusted_string,
+        Err(_) => return false,
+    };
+
+    let skel_path = skel_path.to_string_lossy();
+
+    trusted_string
+        .lines()
+        .any(|line| match line.split_once(' ') {
+            Some((line_hash, line_path)) => {
+                line_hash == hash && line_path == skel_path
+            }
+            None => false,
+        })
+}
+
+fn add_trusted(
+    trusted: &Path,
+    skel_path: &Path,
+    hash: &str,
+) -> Result<(), Box<dyn Error>> {
+    if let Some(parent) = trusted.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut trusted_file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(trusted)?;
+
+    writeln!(trusted_file, "{} {}", hash, skel_path.display())?;
+
+    Ok(())
+}
+
+fn print_scripts(run_conf: &RunConfig) {
+    let skel_conf = &run_conf.skel_conf;
+
+    eprintln!(
+        "{} wants to run the following scripts",
+        run_conf.skel_path.display()
+    );
+
+    if let Some(ref build) = skel_conf.build {
+        eprintln!("  ------");
+        eprintln!("  build first = {}", skel_conf.build_first.unwrap_or(false));
+
+        for line in build.lines() {
+            eprintln!("    {}", line);
+        }
+    }
+
+    for (i, step) in skel_conf.steps.iter().flatten().enumerate() {
+        if let Some(ref run) = step.run {
+            eprintln!("  ------");
+            eprintln!("  steps[{}] run", i);
+
+            for line in run.lines() {
+                eprintln!("    {}", line);
+            }
+        }
+    }
+
+    eprintln!("  ------");
+}
+
+/// make sure the scripts in a skeleton are trusted before running them
+///
+/// the first time a skeleton or a changed script is seen the scripts are
+/// printed and the user is asked to trust them, trusted hashes are kept in
+/// `$XDG_DATA_HOME/skel/trusted`
+pub(crate) fn check_trust(run_conf: &RunConfig) -> Result<(), Box<dyn Error>> {
+    if run_conf.no_build || run_conf.trust {
+        return Ok(());
+    }
+
+    let hash = match scripts_hash(&run_conf.skel_conf) {
+        Some(hash) => hash,
+        None => return Ok(()),
+    };
+
+    let skel_path = fs::canonicalize(&run_conf.skel_path)
+        .unwrap_or_else(|_| run_conf.skel_path.clone());
+
+    let trusted = trusted_path()?;
+
+    if is_trusted(&trusted, &skel_path, &hash) {
+        return Ok(());
+    }
+
+    if !io::stdin().is_terminal() {
+        return Err(Box::from(format!(
+            "refusing to run untrusted scripts from {}, use --trust or \
+            --no-build",
+            skel_path.display()
+        )));
+    }
+
+    print_scripts(run_conf);
+
+    eprint!("trust and run these scripts? [y/N] ");
+    io::stderr().flush()?;
+
+    let mut answer = String::new();
+
+    io::stdin().read_line(&mut answer)?;
+
+    match answer.trim() {
+        "y" | "Y" | "yes" => add_trusted(&trusted, &skel_path, &hash),
+        _ => Err(Box::from(String::from("scripts were not trusted"))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    use crate::{
+        config::{parse_skel_config, SkelStep},
+        test_utils,
+    };
+
+    #[test]
+    fn test_scripts_hash() {
+        let mut skel_conf = SkelConfig::default();
+
+        assert!(
+            scripts_hash(&skel_conf).is_none(),
+            "some how hashed a skeleton with no scripts"
+        );
+
+        skel_conf.build = Some("echo {{name}}".into());
+
+        let build_hash = scripts_hash(&skel_conf).unwrap();
+
+        skel_conf.steps = Some(vec![SkelStep {
+            run: Some("cargo add serde".into()),
+            ..Default::default()
+        }]);
+
+        assert_ne!(
+            scripts_hash(&skel_conf).unwrap(),
+            build_hash,
+            "a new run step did not change the hash"
+        );
+    }
+
+    #[test]
+    fn test_scripts_hash_derived() {
+        let hash = |name: &str, text: &str| {
+            let mut template_data =
+                HashMap::from([("name".to_string(), name.to_string())]);
+
+            let skel_conf = parse_skel_config(
+                Path::new("skeleton.toml"),
+                text,
+                &mut template_data,
+                false,
+            )
+            .unwrap();
+
+            scripts_hash(&skel_conf).unwrap()
+        };
+
+        let skeleton = "build = \"{{cmd}} {{name}}\"\n\
+            [derived]\ncmd = \"echo hi\"\n";
+
+        assert_eq!(
+            hash("one", skeleton),
+            hash("two", skeleton),
+            "a new project name changed the hash"
+        );
+
+        assert_ne!(
+            hash("one", skeleton),
+            hash("one", &skeleton.replace("echo hi", "rm -rf ~")),
+            "a changed derived variable did not change the hash"
+        );
+
+        assert_ne!(
+            hash("one", skeleton),
+            hash("one", &format!("{}[exec]\nclear_env = false\n", skeleton)),
+            "changed exec settings did not change the hash"
+        );
+    }
+
+    #[test]
+    fn test_trusted() {
+        let test_data = test_utils::TestData::default();
+
+        let trusted = test_data.temp_path.join("skel/trusted");
+        let skel_path = Path::new("/tmp/projects/test_project.toml");
+
+        assert!(
+            !is_trusted(&trusted, skel_path, "abc"),
+            "trusted a script with no trusted file"
+        );
+
+        add_trusted(&trusted, skel_path, "abc").unwrap();
+
+        assert!(is_trusted(&trusted, skel_path, "abc"), "did not trust");
+
+        assert!(
+            !is_trusted(&trusted, skel_path, "def"),
+            "trusted a changed script"
+        );
+
+        assert!(
+            !is_trusted(&trusted, Path::new("/tmp/other.toml"), "abc"),
+            "trusted a different skeleton"
+        );
+    }
+}